@@ -11,6 +11,7 @@
    - hide(false) will show all directories traversed
    - recurses directory tree at specified root by default
    - recurse(false) examines only specified path.
+   - stops visiting when App::is_done returns true
 */
 #![allow(unused_imports)]
 use std::fs::{self, DirEntry};
@@ -23,6 +24,10 @@ pub trait DirEvent {
     fn new() -> Self;
     fn do_dir(&mut self, d: &Path);
     fn do_file(&mut self, f: &Path);
+    /// App has all it needs, stop visiting, e.g., a limit was reached
+    fn is_done(&self) -> bool {
+        false
+    }
 }
 //---------------------------------------------------------
 // See examples\test1.rs for Sample implementation of 
@@ -102,6 +107,9 @@ impl<App: DirEvent + Default> DirNav<App> {
     pub fn visit(&mut self, dir: &Path) -> io::Result<()>
     where App: DirEvent
     {
+        if self.app.is_done() {
+            return Ok(());
+        }
         self.app.do_dir(dir);
         self.num_dir += 1;
         let mut sub_dirs = Vec::<PathBuf>::new();
@@ -116,6 +124,9 @@ impl<App: DirEvent + Default> DirNav<App> {
                     self.num_file += 1;
                     if self.in_patterns(&entry) | self.pats.is_empty() {
                         self.app.do_file(&Path::new(&entry.file_name()));
+                        if self.app.is_done() {
+                            return Ok(());
+                        }
                     }
                 }
             }
@@ -230,6 +241,28 @@ mod tests {
         */
         // assert!(rl.contains(&l("foobar")));
     }
+    #[derive(Debug, Default)]
+    struct ApplLimit {
+        files: usize
+    }
+    impl DirEvent for ApplLimit {
+        fn new() -> ApplLimit {
+            ApplLimit::default()
+        }
+        fn do_dir(&mut self, _d: &Path) {}
+        fn do_file(&mut self, _f: &Path) {
+            self.files += 1;
+        }
+        fn is_done(&self) -> bool {
+            self.files >= 2
+        }
+    }
+    #[test]
+    fn test_done() {
+        let mut dn = DirNav::<ApplLimit>::new();
+        let _ = dn.visit(Path::new("."));
+        assert_eq!(dn.get_app().files, 2);
+    }
     #[test]
     fn test_patts() {
         let mut dn = DirNav::<ApplTest>::new();
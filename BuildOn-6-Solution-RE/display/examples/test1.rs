@@ -5,7 +5,7 @@
 /////////////////////////////////////////////////////////////
 
 use display::*;
-use text_search::{SearchEvent};
+use text_search::{SearchEvent, FileResult, FileStatus};
 use std::path::Path;
 
 fn main() {
@@ -13,16 +13,11 @@ fn main() {
     // out.show_all(true);
     // out.set_hide_unmatched(false);
     out.set_dir(Path::new("dir1"));
-    let mut d = (Path::new("file1"), true, "struct");
-    out.set_file(d);
-    d.0 = Path::new("file2");
-    out.set_file(d);
+    out.set_file(&FileResult::new(Path::new("file1"), FileStatus::Matched, "struct"));
+    out.set_file(&FileResult::new(Path::new("file2"), FileStatus::Matched, "struct"));
     out.set_dir(Path::new("dir2"));
     out.set_dir(Path::new("dir3"));
-    d.0 = Path::new("file3");
-    d.1 = false;
-    d.2 = "foobar";
-    out.set_file(d);
+    out.set_file(&FileResult::new(Path::new("file3"), FileStatus::Unmatched, "foobar"));
     
     print!("\n\n  That's all Folks!\n\n");
 }
\ No newline at end of file
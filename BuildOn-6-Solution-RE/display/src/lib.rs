@@ -6,6 +6,7 @@
 
 use text_search::*;
 use dir_nav::{replace_sep};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 /*---------------------------------------------------------
@@ -13,6 +14,7 @@ use std::path::{Path, PathBuf};
   - has options to:
     - show all results or only matches
     - show or hide directories with no matches
+    - show only match counts for each file
 */
 #[derive(Debug, Default)]
 pub struct GenOut {
@@ -20,6 +22,11 @@ pub struct GenOut {
     file: PathBuf,
     txt: String,
     rslt: bool,
+    matches: Vec<Match>,
+    context: Vec<ContextBlock>,
+    count: usize,
+    count_only: bool,
+    inverted: bool,
     show_all: bool,
     hide_unmatched: bool,
     dir_displayed: bool,
@@ -32,6 +39,11 @@ impl SearchEvent for GenOut {
             file: PathBuf::new(),
             txt: String::new(),
             rslt: false,
+            matches: Vec::new(),
+            context: Vec::new(),
+            count: 0,
+            count_only: false,
+            inverted: false,
             show_all: false,
             hide_unmatched: true,
             dir_displayed: false,
@@ -48,26 +60,89 @@ impl SearchEvent for GenOut {
             self.dir_displayed = true;
         }
     }
-    /*-- called by TextSearch::Finder --*/
-    fn set_file(&mut self, rslt:(&Path, bool, &str)) {
-        self.file = rslt.0.to_path_buf();
-        self.txt = rslt.2.to_string();
-        self.rslt = rslt.1;
-        if rslt.1 || self.show_all {
-            if !self.dir_displayed {
-                print!("\n  dir: {:?}", replace_sep(&self.dir));
-                self.dir_displayed = true;
-            }
-            if self.debug {
-                print!("\n    ({:?}, {:?}, {:?})", rslt.0, rslt.1, rslt.2);
+    /*-----------------------------------------------------
+      called by TextSearch::Finder with results for one file
+      - shows file if it matches, or doesn't when inverted,
+        with its matches or context blocks
+      - shows all files searched if show_all is set
+      - files with Error status are shown by set_error
+      - then binary, terms, captures, note, and outcome of
+        replace, if the result has them
+    */
+    fn set_file(&mut self, rslt: &FileResult) {
+        self.file = rslt.path.clone();
+        self.txt = rslt.pattern.clone();
+        if rslt.status == FileStatus::Error {
+            self.rslt = false;
+            return;  /* already shown by set_error */
+        }
+        self.rslt = match rslt.status {
+            FileStatus::Matched => !self.inverted,
+            FileStatus::Unmatched => self.inverted,
+            _ => false
+        };
+        self.count = rslt.count;
+        self.matches = rslt.matches.clone();
+        self.context = rslt.blocks.clone();
+        if !self.rslt && !self.show_all {
+            return;
+        }
+        self.show_dir();
+        if self.count_only {
+            if !self.rslt {
+                print!("\n    {:?}, {:?}", rslt.path, rslt.status);
             }
-            else if self.show_all {
-                print!("\n    {:?}, {}", rslt.0, rslt.1);
+            else if self.inverted {  /* files without matches, so no counts */
+                print!("\n    {:?}", rslt.path);
             }
             else {
-                print!("\n    {:?}", rslt.0);
+                print!("\n    {:?}: {}", rslt.path, rslt.count);
+            }
+            self.show_replaced(&rslt.replaced);
+            return;
+        }
+        if self.debug {
+            print!("\n    ({:?}, {:?}, {:?})", rslt.path, rslt.status, rslt.pattern);
+        }
+        else if self.show_all && !rslt.message.is_empty() {
+            print!("\n    {:?}, {}", rslt.path, rslt.message);
+        }
+        else if self.show_all {
+            print!("\n    {:?}, {}", rslt.path, self.rslt);
+        }
+        else {
+            print!("\n    {:?}", rslt.path);
+        }
+        if self.rslt {
+            self.show_matches(&rslt.matches);
+            self.show_context(&rslt.blocks);
+            self.show_items(&rslt.items);
+            if rslt.binary {
+                print!("\n      binary file matches");
+            }
+            self.show_terms(&rslt.terms);
+            self.show_captures(&rslt.captures);
+            if !rslt.message.is_empty() && (self.debug || !self.show_all) {
+                print!("\n      {}", rslt.message);
             }
         }
+        self.show_replaced(&rslt.replaced);
+    }
+    /*-----------------------------------------------------
+      called by TextSearch::Finder for files it can't read
+      - errors go to stderr, after pending stdout output
+    */
+    fn set_error(&mut self, err: &FileError) {
+        self.file = err.path.clone();
+        self.rslt = false;
+        let _ = std::io::stdout().flush();
+        let path = replace_sep(&self.dir.join(&err.path));
+        if self.debug {
+            eprint!("\n  error: {:?}: {} ({:?})", path, err.message, err.kind);
+        }
+        else {
+            eprint!("\n  error: {:?}: {}", path, err.message);
+        }
     }
 }
 impl GenOut {
@@ -82,6 +157,89 @@ impl GenOut {
     pub fn set_debug(&mut self, db: bool) {
         self.debug = db;
     }
+    /*-- called by Executive based on cmdln opts  --*/
+    pub fn set_count_only(&mut self, c: bool) {
+        self.count_only = c;
+    }
+    /*-- called by Executive, files shown are those without matches --*/
+    pub fn set_inverted(&mut self, inv: bool) {
+        self.inverted = inv;
+    }
+    /*-- show matches, one per line, as "line:col: text" --*/
+    fn show_matches(&self, matches: &[Match]) {
+        for m in matches {
+            if m.end_line > m.line {
+                /*-- multi-line match, continuation lines indented --*/
+                let text = m.text.replace('\n', "\n        ");
+                print!("\n      {}-{}:{}: {}", m.line, m.end_line, m.col, text);
+            }
+            else {
+                print!("\n      {}:{}: {}", m.line, m.col, m.text);
+            }
+            if let Some(d) = m.distance {
+                print!("  <{:?}, distance {}>", m.matched, d);
+            }
+        }
+    }
+    /*-----------------------------------------------------
+      show context blocks
+      - match lines show as "line: text", context lines as
+        "line- text", and blocks are separated by "--"
+    */
+    fn show_context(&self, blocks: &[ContextBlock]) {
+        for (i, block) in blocks.iter().enumerate() {
+            if i > 0 {
+                print!("\n      --");
+            }
+            for cl in block {
+                let sep = if cl.is_match { ':' } else { '-' };
+                print!("\n      {}{} {}", cl.line, sep, cl.text);
+            }
+        }
+    }
+    /*-- show items, one per line, as "line-end_line: kind path" --*/
+    fn show_items(&self, items: &[Item]) {
+        for it in items {
+            print!("\n      {}-{}: {} {}", it.line, it.end_line, it.kind, it.path);
+        }
+    }
+    /*-- show terms found in file, with counts --*/
+    fn show_terms(&self, terms: &[(String, usize)]) {
+        if !terms.is_empty() {
+            let found: Vec<String> = 
+                terms.iter().map(|(t, n)| format!("{} ({})", t, n)).collect();
+            print!("\n      terms: {}", found.join(", "));
+        }
+    }
+    /*-- show capture groups of each match, one match per line --*/
+    fn show_captures(&self, caps: &[CaptureSet]) {
+        for cs in caps {
+            let groups: Vec<String> = cs.groups.iter()
+                .map(|c| format!("{}={:?}", c.name, c.value))
+                .collect();
+            print!("\n      {}: {}", cs.line, groups.join(", "));
+        }
+    }
+    /*-- show diff for dry run, or result of rewriting, in replace mode --*/
+    fn show_replaced(&self, rslt: &Option<Replaced>) {
+        match rslt {
+            Some(Replaced::DryRun(diff)) => {
+                for line in diff.lines() {
+                    print!("\n      {}", line);
+                }
+            },
+            Some(Replaced::Written(n)) => print!("\n      replaced {} matches", n),
+            Some(Replaced::Failed(msg)) => print!("\n      not rewritten: {}", msg),
+            None => {}
+        }
+    }
+    /*-- display current dir once, before its first file --*/
+    fn show_dir(&mut self) {
+        if !self.dir_displayed {
+            print!("\n  dir: {:?}", replace_sep(&self.dir));
+            self.dir_displayed = true;
+        }
+    }
 }
 
 #[cfg(test)]
@@ -94,6 +252,10 @@ mod tests {
         assert_eq!(go.file, PathBuf::new());
         assert_eq!(go.txt, String::new());
         assert_eq!(go.rslt, false);
+        assert!(go.matches.is_empty());
+        assert!(go.context.is_empty());
+        assert_eq!(go.count, 0);
+        assert!(!go.count_only);
         assert_eq!(go.show_all, false);
         assert_eq!(go.hide_unmatched, true);
         assert_eq!(go.dir_displayed, false);
@@ -109,10 +271,82 @@ mod tests {
     fn set_file() {
         let mut go = GenOut::new();
         let path = Path::new("feebar");
-        let arg = (path, true, "search text");
-        go.set_file(arg);
+        let fr = FileResult::new(path, FileStatus::Matched, "search text");
+        go.set_file(&fr);
         assert_eq!(go.file, path);
         assert_eq!(go.rslt, true);
         assert_eq!(go.txt, String::from("search text"));
+        go.set_inverted(true);
+        go.set_file(&fr);
+        assert!(!go.rslt);
+        go.set_file(&FileResult::new(path, FileStatus::Unmatched, "search text"));
+        assert!(go.rslt);
+        go.set_file(&FileResult::new(path, FileStatus::Skipped, "search text"));
+        assert!(!go.rslt);
+    }
+    #[test]
+    fn set_error() {
+        let mut go = GenOut::new();
+        go.set_file(&FileResult::new(Path::new("feebar"), FileStatus::Matched, "text"));
+        assert!(go.rslt);
+        let err = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+        go.set_error(&FileError::new(Path::new("foobar"), "open", &err));
+        assert_eq!(go.file, PathBuf::from("foobar"));
+        assert!(!go.rslt);
+        go.show_all(true);
+        go.set_file(&FileResult::new(Path::new("foobar"), FileStatus::Error, "text"));
+        assert!(!go.rslt);
+    }
+    #[test]
+    fn set_matches() {
+        let mut go = GenOut::new();
+        let m = Match { 
+            line: 3, end_line: 3, col: 5, offset: 42, text: "some text".to_string(),
+            matched: "text".to_string(), distance: None
+        };
+        let mut fr = FileResult::new(Path::new("feebar"), FileStatus::Matched, "text");
+        fr.count = 1;
+        fr.matches = vec![m.clone()];
+        go.set_file(&fr);
+        assert_eq!(go.matches, vec![m]);
+        assert_eq!(go.count, 1);
+    }
+    #[test]
+    fn set_count() {
+        let mut go = GenOut::new();
+        go.set_count_only(true);
+        go.set_dir(Path::new("foobar"));
+        assert!(!go.dir_displayed);
+        let mut fr = FileResult::new(Path::new("feebar"), FileStatus::Matched, "text");
+        fr.count = 3;
+        go.set_file(&fr);
+        assert_eq!(go.count, 3);
+        assert!(go.dir_displayed);
+    }
+    #[test]
+    fn set_context() {
+        let mut go = GenOut::new();
+        let cl = |line: usize, is_match: bool| ContextLine {
+            line, text: format!("line {}", line), is_match
+        };
+        let blocks = vec![
+            vec![cl(1, false), cl(2, true), cl(3, false)],
+            vec![cl(8, false), cl(9, true)]
+        ];
+        let mut fr = FileResult::new(Path::new("feebar"), FileStatus::Matched, "text");
+        fr.blocks = blocks.clone();
+        go.set_file(&fr);
+        assert_eq!(go.context, blocks);
+    }
+    #[test]
+    fn set_extras() {
+        let mut go = GenOut::new();
+        let mut fr = FileResult::new(Path::new("feebar"), FileStatus::Matched, "text");
+        fr.binary = true;
+        fr.terms = vec![("text".to_string(), 2)];
+        fr.replaced = Some(Replaced::Written(2));
+        go.set_file(&fr);
+        assert!(go.rslt);
+        assert_eq!(fr.version, FILE_RESULT_VERSION);
     }
 }
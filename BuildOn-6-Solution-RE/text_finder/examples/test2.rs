@@ -10,6 +10,7 @@
 
 use integration::{Executive};
 use cmdln_parser::{show_parse};
+use text_search::{Limit};
 
 fn main() {
     print!("\n  -- TextFinder, v1.1 --\n");
@@ -36,10 +37,51 @@ fn main() {
     if !ex.start() {
         print!("\n  can't start dir nav\n");
     }
-    print!(
-        "\n\n  processed {} files in {} dirs, {} matches\n", 
-        ex.get_files(), 
-        ex.get_dirs(),
-        ex.get_matches()
-    );
+    if ex.is_inverted() {
+        print!(
+            "\n\n  processed {} files in {} dirs, {} files without matches\n", 
+            ex.get_files(), 
+            ex.get_dirs(),
+            ex.get_matches()
+        );
+    }
+    else {
+        print!(
+            "\n\n  processed {} files in {} dirs, {} matching files, {} matches\n", 
+            ex.get_files(), 
+            ex.get_dirs(),
+            ex.get_matches(),
+            ex.get_occurrences()
+        );
+    }
+    if ex.get_binaries() > 0 {
+        println!("  skipped {} binary files", ex.get_binaries());
+    }
+    if ex.get_large() > 0 {
+        println!("  skipped {} files larger than max size", ex.get_large());
+    }
+    match ex.get_limit() {
+        Some(Limit::Files(n)) => println!("  stopped at file limit, {} files searched", n),
+        Some(Limit::Matches(n)) => println!(
+            "  stopped at match limit of {}, {} matches found", n, ex.get_occurrences()
+        ),
+        None => {}
+    }
+    if ex.get_errors() > 0 {
+        println!("  {} files could not be read", ex.get_errors());
+    }
+    let groups = ex.get_captures();
+    if !groups.is_empty() {
+        print!("\n  captured values:");
+        for (name, values) in groups {
+            print!("\n    group {}:", name);
+            for (value, count) in values {
+                print!("\n      {:?}: {}", value, count);
+            }
+        }
+        println!();
+    }
+    if ex.get_replaced() > 0 {
+        println!("  rewrote {} files", ex.get_replaced());
+    }
 }
\ No newline at end of file
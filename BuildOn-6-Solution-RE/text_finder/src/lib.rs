@@ -8,7 +8,9 @@
 
 /*-- dependencies --*/
 use dir_nav::{DirNav, replace_sep};
-use text_search::{Finder};
+use text_search::{Finder, Boundary, BinaryPolicy, RegexFlags, Limit, NameMode, Scope, ItemKind};
+use text_search::stream::{DEFAULT_BUF_SIZE};
+use text_search::terms::{read_terms};
 use display::{GenOut};
 use cmdln_parser::{CmdParser, show_parse};
 use std::path::{Path, PathBuf};
@@ -70,6 +72,18 @@ impl Executive {
             }
         }
         
+        /* case-insensitive search, applies to both text and regex */
+        if self.get_first("i") == "true" {
+            self.dn.get_app().set_ignore_case(true);
+        }
+
+        /* whole word matching, /w ident treats '_' as word char */
+        match self.get_first("w").as_str() {
+            "true" => self.dn.get_app().set_boundary(Boundary::Word),
+            "ident" | "id" => self.dn.get_app().set_boundary(Boundary::Ident),
+            _ => {}
+        }
+
         /* set search text in Finder */
         if let Some(txts) = self.cp.get("T") {
             if !txts.is_empty() {
@@ -80,15 +94,262 @@ impl Executive {
             }
         }
 
+        /* regex flags, e.g., /Rf ms for multi-line matches */
+        let flags = self.get_first("Rf");
+        if !flags.is_empty() && flags != "true" {
+            match RegexFlags::parse(&flags) {
+                Ok(rf) => self.dn.get_app().set_regex_flags(rf),
+                Err(msg) => {
+                    print!("\n  {}\n\n", msg);
+                    return false;
+                }
+            }
+        }
+
+        /* many literal terms, from /Tm list and /Tl term files */
+        let mut terms = Vec::<String>::new();
+        if let Some(tms) = self.cp.get("Tm") {
+            terms.extend(tms.iter().filter(|t| t.as_str() != "true").cloned());
+        }
+        if let Some(files) = self.cp.get("Tl") {
+            for file in files {
+                if file == "true" {
+                    print!("\n  missing terms file, use /Tl file\n\n");
+                    return false;
+                }
+                match read_terms(Path::new(file)) {
+                    Ok(tms) => terms.extend(tms),
+                    Err(e) => {
+                        print!("\n  can't read terms file {:?}: {}\n\n", file, e);
+                        return false;
+                    }
+                }
+            }
+        }
+        if !terms.is_empty() {
+            if let Err(msg) = self.dn.get_app().set_terms(&terms) {
+                print!("\n  invalid search terms: {}\n\n", msg);
+                return false;
+            }
+            print!("\n  searching for {} terms", terms.len());
+        }
+
+        /* fuzzy text search, /Tf alone allows one edit */
+        let fz = self.get_first("Tf");
+        if !fz.is_empty() {
+            let k = if fz == "true" { Some(1) } else { fz.parse::<usize>().ok() };
+            if k.is_none() {
+                print!("\n  invalid fuzzy distance {:?}\n\n", fz);
+                return false;
+            }
+            self.dn.get_app().set_fuzzy(k);
+        }
+
         /* set search regex in Finder */
         if let Some(txts) = self.cp.get("R") {
             if !txts.is_empty() {
-                self.dn.get_app().set_regex(&txts[0]);
+                if let Err(e) = self.dn.get_app().set_regex(&txts[0]) {
+                    print!("\n  invalid regex {:?}:", txts[0]);
+                    for line in self.dn.get_app().regex_error_msg(&e).lines() {
+                        print!("\n    {}", line);
+                    }
+                    print!("\n\n");
+                    return false;
+                }
                 if !&txts[0].is_empty() {
                     print!("\n  searching with regex: {:?}", txts[0]);
                 }
             }
         }
+        if !self.get_first("T").is_empty() && !self.get_first("R").is_empty() {
+            print!("\n  text ignored when regex given, use /Q to combine them");
+        }
+
+        /* set boolean query in Finder, replaces text and regex */
+        if let Some(qrys) = self.cp.get("Q") {
+            let qry = qrys.join(" ");
+            if !qry.is_empty() && qry != "true" {
+                if let Err(e) = self.dn.get_app().set_query(&qry) {
+                    print!("\n  invalid query: {}", e.msg);
+                    print!("\n    {}\n    {}^\n\n", qry, " ".repeat(e.pos));
+                    return false;
+                }
+                print!("\n  searching with query: {:?}", qry);
+            }
+        }
+        let qry_lines = self.get_first("Qm") == "line";
+        self.dn.get_app().set_query_lines(qry_lines);
+
+        /* replace matches, /Rd shows diffs only, /Rb keeps backups */
+        if let Some(reps) = self.cp.get("Rp") {
+            let rp = reps.join(" ");
+            if rp == "true" {
+                print!("\n  missing replacement, use /Rp \"\" to delete matches\n\n");
+                return false;
+            }
+            self.dn.get_app().set_replace(Some(&rp));
+            let dry_run = self.get_first("Rd") == "true";
+            self.dn.get_app().set_dry_run(dry_run);
+            let backup = self.get_first("Rb");
+            if backup == "true" {
+                self.dn.get_app().set_backup(".bak");
+            }
+            else {
+                self.dn.get_app().set_backup(&backup);
+            }
+            print!("\n  replacing with: {:?}{}", rp, if dry_run { ", dry run" } else { "" });
+        }
+
+        /* set context lines shown before and after matches */
+        let ctx = self.get_first("C").parse::<usize>().unwrap_or(0);
+        let before = self.get_first("CB").parse::<usize>().unwrap_or(ctx);
+        let after = self.get_first("CA").parse::<usize>().unwrap_or(ctx);
+        self.dn.get_app().set_context(before, after);
+
+        /* count only option */
+        let count_only = self.get_first("c") == "true";
+        self.dn.get_app().set_count_only(count_only);
+
+        /* show regex capture groups, /Xa counts distinct values */
+        let extract = self.get_first("X") == "true";
+        self.dn.get_app().set_extract(extract);
+        let aggregate = self.get_first("Xa") == "true";
+        self.dn.get_app().set_aggregate(aggregate);
+
+        /* search inside gzip, xz, and zstd files */
+        let decompress = self.get_first("z") == "true";
+        self.dn.get_app().set_decompress(decompress);
+
+        /* inverted, report files without matches */
+        let inverted = self.get_first("N") == "true";
+        self.dn.get_app().set_invert(inverted);
+
+        /* match file names or paths, not contents, /F alone is name */
+        let nm = match self.get_first("F").as_str() {
+            "" => NameMode::Contents,
+            "true" | "name" => NameMode::Name,
+            "path" => NameMode::Path,
+            other => {
+                print!("\n  invalid file name mode {:?}, use name or path\n\n", other);
+                return false;
+            }
+        };
+        self.dn.get_app().set_name_mode(nm);
+
+        /* match only in scopes of Rust sources, e.g., /S code or /S comments,strings */
+        if let Some(names) = self.cp.get("S") {
+            let mut scopes = Vec::<Scope>::new();
+            for name in names.iter().flat_map(|n| n.split(',')) {
+                match Scope::parse(name.trim()) {
+                    Some(s) => scopes.extend(s),
+                    None => {
+                        print!("\n  invalid scope {:?}, use code, comments, strings, line, block, doc, string, or raw\n\n", name);
+                        return false;
+                    }
+                }
+            }
+            self.dn.get_app().set_scopes(&scopes);
+        }
+
+        /* search Rust items of kinds, e.g., /I fn,trait, /I alone for all kinds */
+        if let Some(names) = self.cp.get("I") {
+            let mut kinds = Vec::<ItemKind>::new();
+            for name in names.iter().flat_map(|n| n.split(',')) {
+                match (name.trim(), ItemKind::parse(name.trim())) {
+                    ("true", _) => {},
+                    (_, Some(kind)) => kinds.push(kind),
+                    (_, None) => {
+                        print!("\n  invalid item kind {:?}, use fn, struct, enum, union, trait,", name);
+                        print!("\n  impl, mod, type, const, static, or macro\n\n");
+                        return false;
+                    }
+                }
+            }
+            self.dn.get_app().set_items(Some(&kinds));
+        }
+
+        /* search files on /j threads, /j alone uses one per core */
+        let jobs = self.get_first("j");
+        if jobs == "true" {
+            let n = std::thread::available_parallelism().map_or(1, |n| n.get());
+            self.dn.get_app().set_threads(n);
+        }
+        else if !jobs.is_empty() {
+            match jobs.parse::<usize>() {
+                Ok(n) if n > 0 => self.dn.get_app().set_threads(n),
+                _ => {
+                    print!("\n  invalid thread count {:?}\n\n", jobs);
+                    return false;
+                }
+            }
+        }
+
+        /* limits: skip files over /Ms bytes, stop after /Mf files or /Mm matches */
+        let max_size = self.get_first("Ms");
+        if !max_size.is_empty() {
+            match parse_size(&max_size) {
+                Some(size) if size > 0 => self.dn.get_app().set_max_size(size as u64),
+                _ => {
+                    print!("\n  invalid max file size {:?}\n\n", max_size);
+                    return false;
+                }
+            }
+        }
+        for (key, what) in [("Mf", "file"), ("Mm", "match")] {
+            let max = self.get_first(key);
+            if max.is_empty() {
+                continue;
+            }
+            let n = match max.parse::<usize>() {
+                Ok(n) if n > 0 => n,
+                _ => {
+                    print!("\n  invalid max {} count {:?}\n\n", what, max);
+                    return false;
+                }
+            };
+            if key == "Mf" {
+                self.dn.get_app().set_max_files(n);
+            }
+            else {
+                self.dn.get_app().set_max_matches(n);
+            }
+        }
+
+        /* stream files in chunks of /b bytes, /b alone uses default */
+        let buf = self.get_first("b");
+        if buf == "true" {
+            self.dn.get_app().set_buffer_size(DEFAULT_BUF_SIZE);
+        }
+        else if !buf.is_empty() {
+            match parse_size(&buf) {
+                Some(size) if size > 0 => self.dn.get_app().set_buffer_size(size),
+                _ => {
+                    print!("\n  invalid buffer size {:?}\n\n", buf);
+                    return false;
+                }
+            }
+        }
+
+        /* decode files with given encoding, default is detect from BOM */
+        let enc = self.get_first("E");
+        if enc != "true" {
+            if let Err(msg) = self.dn.get_app().set_encoding(&enc) {
+                print!("\n  {}\n\n", msg);
+                return false;
+            }
+        }
+
+        /* binary file handling */
+        let bp = match self.get_first("bin").as_str() {
+            "" | "skip" => BinaryPolicy::Skip,
+            "match" => BinaryPolicy::Match,
+            "text" => BinaryPolicy::Text,
+            other => {
+                print!("\n  invalid binary policy {:?}, use skip, match, or text\n\n", other);
+                return false;
+            }
+        };
+        self.dn.get_app().set_binary_policy(bp);
 
         if let Some(recur) = self.cp.get("s") {
             if !recur.is_empty() && recur[0].as_str() == "false" {
@@ -117,6 +378,10 @@ impl Executive {
             }
         }
     
+        /* show only match count for each file */
+        out.set_count_only(count_only);
+        out.set_inverted(inverted);
+
         /* apply Debug ouput attribute */
         if let Some(outs) = self.cp.get("D") {
             if !outs.is_empty() {
@@ -128,17 +393,60 @@ impl Executive {
                 }
             }
         }
+
+        /* check search options before any traversal begins */
+        if let Err(msg) = self.validate() {
+            print!("\n  {}\n\n", msg);
+            return false;
+        }
         true
     }
+    /*-- are Finder's search options usable? --*/
+    pub fn validate(&mut self) -> Result<(), String> {
+        self.dn.get_app().validate()
+    }
     pub fn get_dirs(&self) -> usize {
         self.dn.get_dirs()
     }
     pub fn get_files(&self) -> usize {
         self.dn.get_files()
     }
+    /*-- number of files with matches, or without, if inverted --*/
     pub fn get_matches(&mut self) -> usize {
         self.dn.get_app().get_num_found()
     }
+    /*-- distinct captured values with counts, for each regex group --*/
+    pub fn get_captures(&mut self) -> Vec<(String, Vec<(String, usize)>)> {
+        self.dn.get_app().get_captures().get_groups()
+    }
+    /*-- is search inverted, reporting files without matches? --*/
+    pub fn is_inverted(&mut self) -> bool {
+        self.dn.get_app().get_invert()
+    }
+    /*-- number of matches in all files --*/
+    pub fn get_occurrences(&mut self) -> usize {
+        self.dn.get_app().get_num_occurs()
+    }
+    /*-- number of binary files skipped --*/
+    pub fn get_binaries(&mut self) -> usize {
+        self.dn.get_app().get_num_binary()
+    }
+    /*-- number of files skipped as larger than /Ms --*/
+    pub fn get_large(&mut self) -> usize {
+        self.dn.get_app().get_num_large()
+    }
+    /*-- limit that stopped search, from /Mf or /Mm, if any --*/
+    pub fn get_limit(&mut self) -> Option<Limit> {
+        self.dn.get_app().get_limit()
+    }
+    /*-- number of files that could not be opened or read --*/
+    pub fn get_errors(&mut self) -> usize {
+        self.dn.get_app().get_num_errors()
+    }
+    /*-- number of files rewritten by replace --*/
+    pub fn get_replaced(&mut self) -> usize {
+        self.dn.get_app().get_num_replaced()
+    }
     pub fn get_first(&self, key: &str) -> String {
         if let Some(vals) = self.cp.get(key) {
             if !vals.is_empty() {
@@ -239,11 +547,46 @@ impl Executive {
         let mut hm = String::new();
         hm.push_str("Sample Command Line Format:");
         hm.push_str("\n  /P path /p rs /p h cpp /T text_to_find");
+        hm.push_str("\n  /P path /p rs /Q 'unsafe AND NOT \"// SAFETY\"'");
         hm.push_str("\n\nAttributes:");
         hm.push_str("\n  /P .         => start path is \".\"");
         hm.push_str("\n  /p rs h cpp  => patterns are \"rs\", \"h\", \"cpp\"");
         hm.push_str("\n  /T abc       => search text is \"abc\"");
+        hm.push_str("\n  /Tm a b c    => search for all of terms \"a\", \"b\", \"c\" in one pass");
+        hm.push_str("\n  /Tl file     => read terms from file, one per line, # comments");
+        hm.push_str("\n  /Tf 2        => fuzzy text, at most 2 char edits, /Tf alone is 1");
         hm.push_str("\n  /R abc       => regex is \"abc\"");
+        hm.push_str("\n  /Rf ms       => regex flags i, m, s, x, u, '-' turns off, for /R and re:");
+        hm.push_str("\n  /Rp 'x($1)'  => replace matches, regex captures $1, ${name}");
+        hm.push_str("\n  /Rd true     => dry run, show unified diffs, write nothing");
+        hm.push_str("\n  /Rb .orig    => keep originals as name.orig, /Rb alone uses .bak");
+        hm.push_str("\n  /Q a AND b   => query with AND, OR, NOT, (), \"phrase\", re:regex");
+        hm.push_str("\n  /Qm line     => evaluate query on each line, default is file");
+        hm.push_str("\n  /i true      => ignore case, with Unicode case folding");
+        hm.push_str("\n  /w true      => match whole words only");
+        hm.push_str("\n  /w ident     => match whole identifiers, '_' is word char");
+        hm.push_str("\n  /C 2         => show 2 lines before and after matches");
+        hm.push_str("\n  /CB 2        => show 2 lines before matches");
+        hm.push_str("\n  /CA 2        => show 2 lines after matches");
+        hm.push_str("\n  /c true      => show only match count for each file");
+        hm.push_str("\n  /X true      => show regex capture groups instead of lines");
+        hm.push_str("\n  /Xa true     => count distinct captured values in all files");
+        hm.push_str("\n  /N true      => show only files that don't match");
+        hm.push_str("\n  /F name      => match file names, not contents, /F path for dir/name");
+        hm.push_str("\n  /S code      => in .rs files match only in code, comments, strings,");
+        hm.push_str("\n                  or line, block, doc, string, raw, e.g., /S doc,raw");
+        hm.push_str("\n  /I trait     => find Rust items: fn, struct, enum, union, trait, impl,");
+        hm.push_str("\n                  mod, type, const, static, macro, with names matching");
+        hm.push_str("\n                  /T, /R, or /Q, /I alone finds items of any kind");
+        hm.push_str("\n  /j 4         => search files on 4 threads, /j alone uses all cores");
+        hm.push_str("\n  /b 64K       => stream files in 64K chunks, line by line");
+        hm.push_str("\n  /Ms 10M      => skip files larger than 10M bytes");
+        hm.push_str("\n  /Mf 1000     => stop after searching 1000 files");
+        hm.push_str("\n  /Mm 100      => stop after file where 100th match is found");
+        hm.push_str("\n  /z true      => search inside gzip, xz, and zstd files");
+        hm.push_str("\n  /bin skip    => binary files: skip, match, or text");
+        hm.push_str("\n  /E latin1    => decode files as latin1, default uses BOM, then UTF-8,");
+        hm.push_str("\n                  then windows-1252 for legacy text");
         hm.push_str("\n  /s true      => recursive search");
         hm.push_str("\n  /H true      => hide dirs with no matches");
         hm.push_str("\n  /v true      => verbose - show parse");
@@ -267,6 +610,10 @@ impl Executive {
     }
     /* start DirNav at specified path */
     pub fn start(&mut self) -> bool {
+        if let Err(msg) = self.validate() {
+            print!("\n  {}\n", msg);
+            return false;
+        }
         if let Ok(curr_dir) = std::env::current_dir() {
             print!("\n  current directory:\n    {:?}", replace_sep(&curr_dir));
         }
@@ -278,6 +625,7 @@ impl Executive {
             let start_path = Path::new(&path_string);
             print!("\n  start path:\n    {:?}\n", self.to_abs_path(start_path));
             let rslt = self.dn.visit(start_path);
+            self.dn.get_app().finish();
             println!();
             match rslt {
                 Ok(()) => true,
@@ -289,6 +637,17 @@ impl Executive {
         }
     }
 }
+/*-- parse sizes like "4096", "64K", or "2M" into bytes --*/
+pub fn parse_size(s: &str) -> Option<usize> {
+    let s = s.trim();
+    let (num, mult) = match s.chars().last()? {
+        'k' | 'K' => (&s[..s.len() - 1], 1024),
+        'm' | 'M' => (&s[..s.len() - 1], 1024 * 1024),
+        'g' | 'G' => (&s[..s.len() - 1], 1024 * 1024 * 1024),
+        _ => (s, 1)
+    };
+    num.parse::<usize>().ok()?.checked_mul(mult)
+}
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -304,6 +663,21 @@ mod tests {
         let patts = ex.dn.get_patts();
         assert_eq!(patt, patts[0]);
     }
+    #[test]
+    fn validation() {
+        let mut ex = Executive::new();
+        assert!(ex.validate().is_ok());
+        assert!(ex.dn.get_app().set_regex("(abc").is_err());
+        assert!(ex.validate().is_err());
+    }
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("64K"), Some(64 * 1024));
+        assert_eq!(parse_size("2m"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_size("K"), None);
+        assert_eq!(parse_size("abc"), None);
+    }
     /*-------------------------------------------
       Have to test rest with test mains
       - too many moving parts for simple unit tests
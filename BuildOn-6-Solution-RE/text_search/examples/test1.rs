@@ -47,26 +47,72 @@ impl SearchEvent for GenOut {
             print!("\n--dir: {:?}", replace_sep(dir));
         }
     }
-    /*-- called by TextSearch::Finder --*/
-    fn set_file(&mut self, rslt:(&Path, bool, &str)) {
-        self.file = rslt.0.to_path_buf();
-        self.txt = rslt.2.to_string();
-        self.rslt = rslt.1;
-        if rslt.1 || self.show_all {
+    /*-- called by TextSearch::Finder with results for one file --*/
+    fn set_file(&mut self, rslt: &FileResult) {
+        self.file = rslt.path.clone();
+        self.txt = rslt.pattern.clone();
+        self.rslt = rslt.is_match();
+        if rslt.status == FileStatus::Error {
+            return;  /* shown by set_error */
+        }
+        if self.rslt || self.show_all {
             if !self.dir_displayed {
                 print!("\n  dir: {:?}", replace_sep(&self.dir));
                 self.dir_displayed = true;
             }
             if self.debug {
-                print!("\n    ({:?}, {:?}, {:?})", rslt.0, rslt.1, rslt.2);
+                print!("\n    ({:?}, {:?}, {:?})", rslt.path, rslt.status, rslt.pattern);
             }
             else if self.show_all {
-                print!("\n    {:?}, {}", rslt.0, rslt.1);
+                print!("\n    {:?}, {}", rslt.path, self.rslt);
             }
             else {
-                print!("\n    {:?}", rslt.0);
+                print!("\n    {:?}", rslt.path);
             }
         }
+        if self.rslt {
+            for m in &rslt.matches {
+                print!("\n      {}:{}: {}", m.line, m.col, m.text);
+            }
+            for (i, block) in rslt.blocks.iter().enumerate() {
+                if i > 0 {
+                    print!("\n      --");
+                }
+                for cl in block {
+                    let sep = if cl.is_match { ':' } else { '-' };
+                    print!("\n      {}{} {}", cl.line, sep, cl.text);
+                }
+            }
+            if rslt.binary {
+                print!("\n      binary file matches");
+            }
+            if !rslt.terms.is_empty() {
+                let found: Vec<String> = 
+                    rslt.terms.iter().map(|(t, n)| format!("{} ({})", t, n)).collect();
+                print!("\n      terms: {}", found.join(", "));
+            }
+            for cs in &rslt.captures {
+                let groups: Vec<String> = cs.groups.iter()
+                    .map(|c| format!("{}={:?}", c.name, c.value))
+                    .collect();
+                print!("\n      {}: {}", cs.line, groups.join(", "));
+            }
+        }
+        match &rslt.replaced {
+            Some(Replaced::DryRun(diff)) => {
+                for line in diff.lines() {
+                    print!("\n      {}", line);
+                }
+            },
+            Some(Replaced::Written(n)) => print!("\n      replaced {} matches", n),
+            Some(Replaced::Failed(msg)) => print!("\n      not rewritten: {}", msg),
+            None => {}
+        }
+    }
+    /*-- called by TextSearch::Finder for files it can't read --*/
+    fn set_error(&mut self, err: &FileError) {
+        self.rslt = false;
+        eprint!("\n  error: {:?}: {}", self.dir.join(&err.path), err.message);
     }
 }
 impl GenOut {
@@ -88,14 +134,14 @@ fn find_text(t:&str) {
     print!("\n  searching for text: {:?}", t);
     let mut ts = Finder::<GenOut>::new();
     ts.set_txt(t);
-    ts.do_dir(&Path::new(".\\src"));
-    ts.do_file(&Path::new("lib.rs"));
-    ts.do_dir(&Path::new(".\\examples"));
-    ts.do_file(&Path::new("test1.rs"));
+    ts.do_dir(Path::new(".\\src"));
+    ts.do_file(Path::new("lib.rs"));
+    ts.do_dir(Path::new(".\\examples"));
+    ts.do_file(Path::new("test1.rs"));
     /* these should not find */
-    ts.do_file(&Path::new("no_exist"));
+    ts.do_file(Path::new("no_exist"));
     ts.set_txt("foo_bar");
-    ts.do_file(&Path::new("lib.rs"));
+    ts.do_file(Path::new("lib.rs"));
     println!();
     let nf = ts.get_num_found();
     print!("\n  found {} matches", nf);
@@ -106,15 +152,18 @@ fn match_regex(t:&str) {
     print!("\n  -- testing regex match --");
     print!("\n  searching with regex: {:?}", t);
     let mut ts = Finder::<GenOut>::new();
-    ts.set_regex(t);
-    ts.do_dir(&Path::new(".\\src"));
-    ts.do_file(&Path::new("lib.rs"));
-    ts.do_dir(&Path::new(".\\examples"));
-    ts.do_file(&Path::new("test1.rs"));
+    if let Err(e) = ts.set_regex(t) {
+        print!("\n  {}\n", e);
+        return;
+    }
+    ts.do_dir(Path::new(".\\src"));
+    ts.do_file(Path::new("lib.rs"));
+    ts.do_dir(Path::new(".\\examples"));
+    ts.do_file(Path::new("test1.rs"));
     /* these should not match */
-    ts.do_file(&Path::new("no_exist"));
-    ts.set_regex("foo_bar");
-    ts.do_file(&Path::new("lib.rs"));
+    ts.do_file(Path::new("no_exist"));
+    let _ = ts.set_regex("foo_bar");
+    ts.do_file(Path::new("lib.rs"));
     println!();
     let nf = ts.get_num_found();
     print!("\n  found {} matches", nf);
@@ -122,10 +171,10 @@ fn match_regex(t:&str) {
 }
 
 fn main() {
-    print!("\n  {}","-- demo text_search package --\n");
+    print!("\n  -- demo text_search package --\n");
 
     find_text("DirEvent");
     match_regex("DirEvent|main");
 
-    print!("\n  {}","That's all Folks!\n\n");
+    print!("\n  That's all Folks!\n\n");
 }
\ No newline at end of file
@@ -0,0 +1,161 @@
+/////////////////////////////////////////////////////////////
+// TextFinder::text_search::capture.rs                     //
+//   - extract and tally regex capture groups              //
+/////////////////////////////////////////////////////////////
+/*
+  Extraction mode sends the capture groups of each regex
+  match to output, instead of the matching lines.
+  - groups are named by their regex name, e.g., (?P<ver>..),
+    or their number, if unnamed
+  - a regex without groups reports the whole match, as
+    group 0
+  - groups that don't take part in a match are left out
+
+  CaptureTally aggregates distinct captured values, with
+  counts, across all files searched.
+*/
+
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+
+/*-- one captured group of a match --*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Capture {
+    pub index: usize,
+    pub name: String,
+    pub value: String
+}
+
+/*-- captures of one match, with 1-based line of match start --*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CaptureSet {
+    pub line: usize,
+    pub groups: Vec<Capture>
+}
+
+/*---------------------------------------------------------
+  Capture sets for matches of re at spans in buffer
+  - spans are in order, as Finder returns them
+  - first_line is line number of start of buffer, so lines
+    streamed one at a time report their own line number
+*/
+pub fn capture_sets(
+    re: &Regex, buffer: &str, spans: &[(usize, usize)], first_line: usize
+) -> Vec<CaptureSet> {
+    let names: Vec<Option<&str>> = re.capture_names().collect();
+    let mut sets = Vec::<CaptureSet>::new();
+    let mut line = first_line;
+    let mut pos = 0usize;
+    for &(start, _end) in spans {
+        line += buffer[pos..start].bytes().filter(|&b| b == b'\n').count();
+        pos = start;
+        let caps = match re.captures_at(buffer, start) {
+            Some(caps) => caps,
+            None => continue
+        };
+        let first = if names.len() > 1 { 1 } else { 0 };
+        let groups = (first..names.len()).filter_map(|i| {
+            caps.get(i).map(|m| Capture {
+                index: i,
+                name: names[i].map_or(i.to_string(), |n| n.to_string()),
+                value: m.as_str().to_string()
+            })
+        }).collect();
+        sets.push(CaptureSet { line, groups });
+    }
+    sets
+}
+
+/*---------------------------------------------------------
+  Counts of distinct values captured by each group
+*/
+#[derive(Debug, Clone, Default)]
+pub struct CaptureTally {
+    groups: BTreeMap<(usize, String), HashMap<String, usize>>
+}
+impl CaptureTally {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn add(&mut self, sets: &[CaptureSet]) {
+        for set in sets {
+            for cap in &set.groups {
+                *self.groups.entry((cap.index, cap.name.clone()))
+                            .or_default()
+                            .entry(cap.value.clone())
+                            .or_insert(0) += 1;
+            }
+        }
+    }
+    /*-- add counts from other, e.g., tallied on another thread --*/
+    pub fn merge(&mut self, other: &CaptureTally) {
+        for (group, values) in &other.groups {
+            let counts = self.groups.entry(group.clone()).or_default();
+            for (value, n) in values {
+                *counts.entry(value.clone()).or_insert(0) += n;
+            }
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+    /*-----------------------------------------------------
+      (group name, values with counts) in group order
+      - values ordered by count, most frequent first, then
+        by value
+    */
+    pub fn get_groups(&self) -> Vec<(String, Vec<(String, usize)>)> {
+        self.groups.iter().map(|((_, name), values)| {
+            let mut vals: Vec<(String, usize)> =
+                values.iter().map(|(v, &n)| (v.clone(), n)).collect();
+            vals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            (name.clone(), vals)
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn spans(re: &Regex, buffer: &str) -> Vec<(usize, usize)> {
+        re.find_iter(buffer).map(|m| (m.start(), m.end())).collect()
+    }
+    #[test]
+    fn extract() {
+        let buffer = "[package]\nversion = \"0.1.0\"\n\nregex = \"1\"\n";
+        let re = Regex::new(r#"(?P<key>\w+) = "([^"]*)""#).unwrap();
+        let sets = capture_sets(&re, buffer, &spans(&re, buffer), 1);
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets[0].line, 2);
+        assert_eq!(sets[1].line, 4);
+        let names: Vec<&str> = sets[1].groups.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["key", "2"]);
+        assert_eq!(sets[1].groups[1].value, "1");
+        /* no groups, whole match is group 0 */
+        let re = Regex::new(r"\d+\.\d+").unwrap();
+        let sets = capture_sets(&re, buffer, &spans(&re, buffer), 10);
+        assert_eq!(sets[0].line, 11);
+        assert_eq!(sets[0].groups, vec![Capture { index: 0, name: "0".to_string(), value: "0.1".to_string() }]);
+        /* optional group that doesn't match is left out */
+        let re = Regex::new(r"(x)?regex").unwrap();
+        let sets = capture_sets(&re, buffer, &spans(&re, buffer), 1);
+        assert!(sets[0].groups.is_empty());
+    }
+    #[test]
+    fn tally() {
+        let re = Regex::new(r#"version = "([^"]*)""#).unwrap();
+        let mut t = CaptureTally::new();
+        assert!(t.is_empty());
+        for buffer in ["version = \"1.0\"", "version = \"2.0\"\nversion = \"1.0\"", "x"] {
+            t.add(&capture_sets(&re, buffer, &spans(&re, buffer), 1));
+        }
+        let groups = t.get_groups();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].0, "1");
+        assert_eq!(groups[0].1, vec![("1.0".to_string(), 2), ("2.0".to_string(), 1)]);
+        let mut all = CaptureTally::new();
+        all.merge(&t);
+        all.merge(&t);
+        assert_eq!(all.get_groups()[0].1[0], ("1.0".to_string(), 4));
+    }
+}
@@ -0,0 +1,194 @@
+/////////////////////////////////////////////////////////////
+// TextFinder::text_search::compress.rs                    //
+//   - search inside gzip, xz, and zstd compressed files   //
+/////////////////////////////////////////////////////////////
+/*
+  Compressed files are recognized by their magic bytes, not
+  their extension, and read through a decoder, so Finder
+  searches the decompressed text.
+  - all are decoded as they are read, so streaming search
+    keeps memory bounded
+  - decompressed bytes are capped, so a small file that
+    expands hugely fails with a TooLarge error instead of
+    running on without end
+  - all decoders are pure Rust
+*/
+
+use flate2::read::MultiGzDecoder;
+use lzma_rust2::XzReader;
+use ruzstd::decoding::StreamingDecoder;
+use std::fmt;
+use std::io::{self, Cursor, Read};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Xz,
+    Zstd
+}
+
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+const XZ_MAGIC: &[u8] = &[0xFD, b'7', b'z', b'X', b'Z', 0x00];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+
+/*-- default cap on decompressed bytes of one file --*/
+pub const MAX_DECOMPRESSED: u64 = 1 << 30;
+/*-- most memory, in KiB, xz decoder may use for its dictionary --*/
+const XZ_MEM_LIMIT_KB: u32 = 256 * 1024;
+
+/*-- error sent when decompressed data passes its cap --*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooLarge(pub u64);
+impl fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "decompressed size larger than {} bytes", self.0)
+    }
+}
+impl std::error::Error for TooLarge {}
+
+/*-- cap that stopped reading, if err is a TooLarge error --*/
+pub fn too_large(err: &io::Error) -> Option<TooLarge> {
+    err.get_ref().and_then(|e| e.downcast_ref::<TooLarge>()).copied()
+}
+
+/*-- compression used for data starting with head, if any --*/
+pub fn detect(head: &[u8]) -> Option<Compression> {
+    if head.starts_with(GZIP_MAGIC) {
+        Some(Compression::Gzip)
+    }
+    else if head.starts_with(XZ_MAGIC) {
+        Some(Compression::Xz)
+    }
+    else if head.starts_with(ZSTD_MAGIC) {
+        Some(Compression::Zstd)
+    }
+    else {
+        None
+    }
+}
+
+/*---------------------------------------------------------
+  Wrap rdr in a decoder if its data is compressed
+  - returns reader of decompressed bytes, or of the original
+    bytes if not compressed, with the compression found
+  - reading more than max decompressed bytes fails with a
+    TooLarge error, see too_large
+*/
+pub fn reader<'a, R: Read + 'a>(
+    mut rdr: R, max: u64
+) -> io::Result<(Box<dyn Read + 'a>, Option<Compression>)> {
+    let mut head = Vec::<u8>::with_capacity(XZ_MAGIC.len());
+    (&mut rdr).take(XZ_MAGIC.len() as u64).read_to_end(&mut head)?;
+    let comp = detect(&head);
+    let src = Cursor::new(head).chain(rdr);
+    let rdr: Box<dyn Read + 'a> = match comp {
+        None => return Ok((Box::new(src), None)),
+        Some(Compression::Gzip) => Box::new(MultiGzDecoder::new(src)),
+        Some(Compression::Zstd) => {
+            let dec = StreamingDecoder::new(src)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            Box::new(dec)
+        },
+        Some(Compression::Xz) => Box::new(XzReader::new_mem_limit(src, true, XZ_MEM_LIMIT_KB))
+    };
+    Ok((Box::new(Capped { rdr, left: max, max }), comp))
+}
+
+/*-- reader failing with TooLarge after max bytes --*/
+struct Capped<R: Read> {
+    rdr: R,
+    left: u64,
+    max: u64
+}
+impl<R: Read> Read for Capped<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.left == 0 {
+            /* at cap, fail only if there is more */
+            if self.rdr.read(&mut [0u8; 1])? > 0 {
+                return Err(io::Error::other(TooLarge(self.max)));
+            }
+            return Ok(0);
+        }
+        let len = (buf.len() as u64).min(self.left) as usize;
+        let n = self.rdr.read(&mut buf[..len])?;
+        self.left -= n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    const TEXT: &str = "first line\nneedle in line two\n";
+    fn read_all(bytes: &[u8]) -> (String, Option<Compression>) {
+        let (mut rdr, comp) = reader(bytes, MAX_DECOMPRESSED).unwrap();
+        let mut text = String::new();
+        rdr.read_to_string(&mut text).unwrap();
+        (text, comp)
+    }
+    fn xz_bytes(text: &[u8]) -> Vec<u8> {
+        let opts = lzma_rust2::XzOptions::with_preset(6);
+        let mut enc = lzma_rust2::XzWriter::new(Vec::new(), opts).unwrap();
+        enc.write_all(text).unwrap();
+        enc.finish().unwrap()
+    }
+    #[test]
+    fn plain() {
+        assert_eq!(read_all(TEXT.as_bytes()), (TEXT.to_string(), None));
+        assert_eq!(read_all(b"ab"), ("ab".to_string(), None));
+    }
+    #[test]
+    fn gzip() {
+        let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        enc.write_all(TEXT.as_bytes()).unwrap();
+        let bytes = enc.finish().unwrap();
+        assert_eq!(read_all(&bytes), (TEXT.to_string(), Some(Compression::Gzip)));
+    }
+    #[test]
+    fn xz() {
+        let bytes = xz_bytes(TEXT.as_bytes());
+        assert_eq!(read_all(&bytes), (TEXT.to_string(), Some(Compression::Xz)));
+        /* decoded as read: start is available from part of input */
+        let mut big = TEXT.as_bytes().to_vec();
+        let mut seed = 1u32;
+        for _ in 0..1_000_000 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            big.push(b'a' + (seed >> 16) as u8 % 26);
+        }
+        let bytes = xz_bytes(&big);
+        let (mut rdr, _) = reader(&bytes[..bytes.len() / 2], MAX_DECOMPRESSED).unwrap();
+        let mut first = [0u8; 10];
+        rdr.read_exact(&mut first).unwrap();
+        assert_eq!(&first, b"first line");
+    }
+    #[test]
+    fn zstd() {
+        let bytes = ruzstd::encoding::compress_to_vec(
+            TEXT.as_bytes(), ruzstd::encoding::CompressionLevel::Fastest
+        );
+        assert_eq!(read_all(&bytes), (TEXT.to_string(), Some(Compression::Zstd)));
+    }
+    #[test]
+    fn capped() {
+        let bytes = xz_bytes(TEXT.as_bytes());
+        let (mut rdr, _) = reader(&bytes[..], 8).unwrap();
+        let err = rdr.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(too_large(&err), Some(TooLarge(8)));
+        let (mut rdr, _) = reader(&bytes[..], TEXT.len() as u64).unwrap();
+        assert!(rdr.read_to_end(&mut Vec::new()).is_ok());
+        /* plain files are not capped here, /M checks them */
+        let (mut rdr, _) = reader(TEXT.as_bytes(), 8).unwrap();
+        assert!(rdr.read_to_end(&mut Vec::new()).is_ok());
+        assert!(too_large(&io::Error::other("other")).is_none());
+    }
+    #[test]
+    fn corrupt() {
+        let (mut rdr, comp) = reader(&[0x1Fu8, 0x8B, 0, 0, 1, 2][..], MAX_DECOMPRESSED).unwrap();
+        assert_eq!(comp, Some(Compression::Gzip));
+        assert!(rdr.read_to_end(&mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+/////////////////////////////////////////////////////////////
+// TextFinder::text_search::encoding.rs                    //
+//   - decode non-UTF-8 text files to UTF-8                //
+/////////////////////////////////////////////////////////////
+/*
+  Files are decoded when they start with a byte order mark,
+  UTF-8, UTF-16LE, or UTF-16BE, or when an encoding is given
+  explicitly, e.g., windows-1252 for legacy sources.
+  - malformed sequences are replaced with U+FFFD, so a bad
+    byte never stops a search
+  - other files are UTF-8, binary, or, if neither, legacy
+    text decoded as LEGACY, see is_binary in lib.rs
+  - match positions are reported in the decoded UTF-8 text
+
+  DecodeReader does the same decoding for streaming search.
+*/
+
+use encoding_rs::{Decoder, Encoding, CoderResult};
+use std::io::{self, Read};
+
+/*-- encoding of text that is not UTF-8 or binary, every byte decodes --*/
+pub const LEGACY: &Encoding = encoding_rs::WINDOWS_1252;
+
+/*-- find encoding for labels like "utf-16le", "latin1", "windows-1252" --*/
+pub fn encoding_for(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes())
+}
+
+/*---------------------------------------------------------
+  Encoding to decode with, if any
+  - forced encoding overrides byte order mark
+*/
+pub fn select_encoding(
+    head: &[u8], forced: Option<&'static Encoding>
+) -> Option<&'static Encoding> {
+    forced.or_else(|| Encoding::for_bom(head).map(|(enc, _)| enc))
+}
+
+/*-- decode whole file, removing byte order mark of enc --*/
+pub fn decode(bytes: &[u8], enc: &'static Encoding) -> String {
+    let (text, _had_errors) = enc.decode_with_bom_removal(bytes);
+    text.into_owned()
+}
+
+/*---------------------------------------------------------
+  DecodeReader wraps a reader of encoded bytes and reads
+  out UTF-8, decoding in chunks
+*/
+pub struct DecodeReader<R: Read> {
+    rdr: R,
+    decoder: Decoder,
+    inbuf: Vec<u8>,
+    in_start: usize,
+    in_end: usize,
+    outbuf: Vec<u8>,
+    out_start: usize,
+    out_end: usize,
+    eof: bool,
+    done: bool
+}
+impl<R: Read> DecodeReader<R> {
+    pub fn new(rdr: R, enc: &'static Encoding, buf_size: usize) -> Self {
+        let buf_size = buf_size.max(16);
+        DecodeReader {
+            rdr,
+            decoder: enc.new_decoder_with_bom_removal(),
+            inbuf: vec![0u8; buf_size],
+            in_start: 0,
+            in_end: 0,
+            outbuf: vec![0u8; 3 * buf_size + 16],
+            out_start: 0,
+            out_end: 0,
+            eof: false,
+            done: false
+        }
+    }
+}
+impl<R: Read> Read for DecodeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.out_start < self.out_end {
+                let n = buf.len().min(self.out_end - self.out_start);
+                buf[..n].copy_from_slice(&self.outbuf[self.out_start..self.out_start + n]);
+                self.out_start += n;
+                return Ok(n);
+            }
+            if self.done {
+                return Ok(0);
+            }
+            if self.in_start == self.in_end && !self.eof {
+                let n = self.rdr.read(&mut self.inbuf)?;
+                self.in_start = 0;
+                self.in_end = n;
+                self.eof = n == 0;
+            }
+            let (rslt, read, written, _had_errors) = self.decoder.decode_to_utf8(
+                &self.inbuf[self.in_start..self.in_end], &mut self.outbuf, self.eof
+            );
+            self.in_start += read;
+            self.out_start = 0;
+            self.out_end = written;
+            if self.eof && rslt == CoderResult::InputEmpty {
+                self.done = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn utf16le(s: &str) -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xFE];
+        for u in s.encode_utf16() {
+            bytes.extend_from_slice(&u.to_le_bytes());
+        }
+        bytes
+    }
+    #[test]
+    fn select() {
+        let bytes = utf16le("abc");
+        assert_eq!(select_encoding(&bytes, None), Some(encoding_rs::UTF_16LE));
+        assert_eq!(select_encoding(b"abc", None), None);
+        let latin1 = encoding_for("latin1");
+        assert_eq!(select_encoding(&bytes, latin1), latin1);
+        assert!(encoding_for("no-such-encoding").is_none());
+    }
+    #[test]
+    fn decode_whole() {
+        assert_eq!(decode(&utf16le("line\ncaf\u{e9}"), encoding_rs::UTF_16LE), "line\ncaf\u{e9}");
+        let win = encoding_for("windows-1252").unwrap();
+        assert_eq!(decode(b"caf\xe9 \x93q\x94", win), "caf\u{e9} \u{201c}q\u{201d}");
+    }
+    #[test]
+    fn decode_reader() {
+        let text = "first line\nsecond caf\u{e9} line\n".repeat(20);
+        let bytes = utf16le(&text);
+        let mut rdr = DecodeReader::new(&bytes[..], encoding_rs::UTF_16LE, 16);
+        let mut out = String::new();
+        rdr.read_to_string(&mut out).unwrap();
+        assert_eq!(out, text);
+    }
+}
@@ -0,0 +1,204 @@
+/////////////////////////////////////////////////////////////
+// TextFinder::text_search::fuzzy.rs                       //
+//   - approximate text matching with edit distance        //
+/////////////////////////////////////////////////////////////
+/*
+  Finds substrings within Levenshtein distance k of a
+  pattern, counting single char inserts, deletes, and
+  substitutions.
+  - uses Sellers' dynamic programming, one column per char
+    of text, tracking where each partial match started
+  - matches don't cross lines, so lines are searched one at
+    a time, and lines without any of k+1 exact pieces of the
+    pattern are skipped: a match with at most k edits must
+    contain one of the pieces unchanged
+  - pieces are found with one regex alternation, built
+    once per Fuzzy, case-insensitive if ignore_case, so
+    lines are not copied or lowercased; case is folded
+    only char by char, in lines that pass the filter
+  - overlapping candidates are resolved in favor of the
+    smallest distance, then the longest match with the same
+    start
+*/
+
+use crate::{build_regex, RegexFlags};
+use regex::Regex;
+
+/*-- chars equal, with simple case folding if ignore_case --*/
+fn chars_eq(a: char, b: char, ignore_case: bool) -> bool {
+    a == b || (ignore_case && a.to_lowercase().eq(b.to_lowercase()))
+}
+
+/*-- Levenshtein distance between a and b --*/
+pub fn distance(a: &str, b: &str, ignore_case: bool) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0usize; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let sub = prev[j] + usize::from(!chars_eq(ca, cb, ignore_case));
+            curr[j + 1] = sub.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+/*---------------------------------------------------------
+  Split pattern into k+1 pieces of nearly equal length
+  - empty if pattern has fewer than k+1 chars, so no filter
+*/
+fn pieces(pattern: &[char], k: usize) -> Vec<String> {
+    let n = k + 1;
+    if pattern.len() < n {
+        return Vec::new();
+    }
+    (0..n).map(|i| {
+        pattern[i * pattern.len() / n..(i + 1) * pattern.len() / n].iter().collect()
+    }).collect()
+}
+
+/*---------------------------------------------------------
+  Fuzzy search for one pattern, with its line filter built
+  once, so it can be applied to many buffers
+*/
+#[derive(Debug, Clone)]
+pub struct Fuzzy {
+    pat: Vec<char>,
+    k: usize,
+    ignore_case: bool,
+    filter: Option<Regex>
+}
+impl Fuzzy {
+    pub fn new(pattern: &str, k: usize, ignore_case: bool) -> Self {
+        let pat: Vec<char> = pattern.chars().collect();
+        let pcs = pieces(&pat, k);
+        let filter =
+            if pcs.is_empty() {
+                None
+            }
+            else {
+                let alts: Vec<String> = pcs.iter().map(|p| regex::escape(p)).collect();
+                Some(build_regex(&alts.join("|"), &RegexFlags::default(), ignore_case)
+                        .expect("escaped pieces are valid regex"))
+            };
+        Fuzzy { pat, k, ignore_case, filter }
+    }
+    /*-----------------------------------------------------
+      Byte spans (start, end) of non-overlapping matches of
+      pattern in buffer with at most k edits
+    */
+    pub fn find(&self, buffer: &str) -> Vec<(usize, usize)> {
+        let mut spans = Vec::<(usize, usize)>::new();
+        if self.pat.is_empty() {
+            return spans;
+        }
+        let mut line_start = 0usize;
+        for line in buffer.split_inclusive('\n') {
+            let text = line.trim_end_matches(['\n', '\r']);
+            if self.filter.as_ref().is_none_or(|re| re.is_match(text)) {
+                find_in_line(text, &self.pat, self.k, self.ignore_case, line_start, &mut spans);
+            }
+            line_start += line.len();
+        }
+        spans
+    }
+}
+
+/*-- spans of pattern in buffer with at most k edits, see Fuzzy::find --*/
+pub fn find(buffer: &str, pattern: &str, k: usize, ignore_case: bool) -> Vec<(usize, usize)> {
+    Fuzzy::new(pattern, k, ignore_case).find(buffer)
+}
+
+/*-- Sellers' algorithm on one line, spans offset by base --*/
+fn find_in_line(
+    text: &str, pat: &[char], k: usize, ignore_case: bool,
+    base: usize, spans: &mut Vec<(usize, usize)>
+) {
+    let m = pat.len();
+    /* cost[i], start[i]: best distance of pat[..i] ending here, and its start */
+    let mut cost: Vec<usize> = (0..=m).collect();
+    let mut start = vec![0usize; m + 1];
+    let mut best: Option<(usize, usize, usize)> = None;  /* (start, end, dist) */
+    for (pos, ch) in text.char_indices() {
+        let end = pos + ch.len_utf8();
+        let (mut diag_cost, mut diag_start) = (cost[0], start[0]);
+        start[0] = end;  /* empty prefix of pattern matches after this char */
+        for i in 1..=m {
+            let (up_cost, up_start) = (cost[i], start[i]);
+            let mut c = diag_cost + usize::from(!chars_eq(pat[i - 1], ch, ignore_case));
+            let mut s = diag_start;
+            if up_cost + 1 < c {
+                c = up_cost + 1;
+                s = up_start;
+            }
+            if cost[i - 1] + 1 < c {
+                c = cost[i - 1] + 1;
+                s = start[i - 1];
+            }
+            diag_cost = up_cost;
+            diag_start = up_start;
+            cost[i] = c;
+            start[i] = s;
+        }
+        if cost[m] <= k && start[m] < end {
+            let cand = (start[m], end, cost[m]);
+            best = match best {
+                Some(b) if cand.0 < b.1 => {  /* overlaps best so far */
+                    let better = cand.2 < b.2 || (cand.2 == b.2 && cand.0 == b.0);
+                    if better { Some(cand) } else { Some(b) }
+                },
+                Some(b) => {
+                    spans.push((base + b.0, base + b.1));
+                    Some(cand)
+                },
+                None => Some(cand)
+            };
+        }
+    }
+    if let Some(b) = best {
+        spans.push((base + b.0, base + b.1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn found<'a>(buffer: &'a str, pattern: &str, k: usize, ic: bool) -> Vec<&'a str> {
+        find(buffer, pattern, k, ic).iter().map(|&(s, e)| &buffer[s..e]).collect()
+    }
+    #[test]
+    fn distances() {
+        assert_eq!(distance("invalid", "unvalid", false), 1);
+        assert_eq!(distance("kitten", "sitting", false), 3);
+        assert_eq!(distance("", "abc", false), 3);
+        assert_eq!(distance("Finder", "finder", false), 1);
+        assert_eq!(distance("Finder", "finder", true), 0);
+    }
+    #[test]
+    fn fuzzy_find() {
+        let buffer = "if unvalid { return; }\nlet invalid = 1;\nlet valid = 2;\n";
+        assert_eq!(found(buffer, "invalid", 0, false), vec!["invalid"]);
+        assert_eq!(found(buffer, "invalid", 1, false), vec!["unvalid", "invalid"]);
+        assert_eq!(found(buffer, "INVALID", 1, true), vec!["unvalid", "invalid"]);
+        assert_eq!(found(buffer, "invalid", 2, false), vec!["unvalid", "invalid", "t valid"]);
+        assert!(found(buffer, "invalid", 1, false).iter().all(|t| distance("invalid", t, false) <= 1));
+        /* offsets are into whole buffer, multi-byte chars count once */
+        let buffer = "x\ncafé caff\n";
+        let spans = find(buffer, "café", 1, false);
+        assert_eq!(spans, vec![(2, 7), (8, 12)]);
+    }
+    #[test]
+    fn filter() {
+        let pat: Vec<char> = "abcdef".chars().collect();
+        assert_eq!(pieces(&pat, 2), vec!["ab", "cd", "ef"]);
+        assert!(pieces(&pat, 6).is_empty());
+        assert!(found("zzzzzz\n", "abcdef", 2, false).is_empty());
+        /* filter folds case, and non-ASCII, without lowercased copies */
+        let fz = Fuzzy::new("\u{c9}T\u{c9}S", 1, true);
+        assert!(fz.filter.as_ref().unwrap().is_match("les \u{e9}t\u{e9}s"));
+        assert_eq!(fz.find("x\nles \u{e9}t\u{e9}s\n").len(), 1);
+        assert!(Fuzzy::new("\u{c9}T\u{c9}S", 1, false).find("les \u{e9}t\u{e9}s").is_empty());
+    }
+}
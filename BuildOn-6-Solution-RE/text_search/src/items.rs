@@ -0,0 +1,467 @@
+/////////////////////////////////////////////////////////////
+// TextFinder::text_search::items.rs                       //
+//   - find item definitions in Rust source                //
+/////////////////////////////////////////////////////////////
+/*
+  Item search matches definitions of fns, types, traits,
+  impls, and modules, instead of lines of text.
+  - comments and strings are blanked out first, using the
+    lexer in scope.rs, so braces in them don't count
+  - items are found by keyword, with attributes, pub, and
+    qualifiers like async or unsafe skipped, not by full
+    parsing, so code need not compile
+  - path of an item is made from the modules, traits, and
+    impl types enclosing it, e.g., tests::MockOut::new, and
+    an impl is shown as <Trait for Type>
+  - items in fn bodies are found too, with the fn in their
+    path
+*/
+
+use crate::scope::{regions, Scope};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Fn,
+    Struct,
+    Enum,
+    Union,
+    Trait,
+    Impl,
+    Mod,
+    Type,
+    Const,
+    Static,
+    Macro
+}
+impl ItemKind {
+    /*-- kind for name used on command line --*/
+    pub fn parse(name: &str) -> Option<ItemKind> {
+        let kind = match name {
+            "fn" => ItemKind::Fn,
+            "struct" => ItemKind::Struct,
+            "enum" => ItemKind::Enum,
+            "union" => ItemKind::Union,
+            "trait" => ItemKind::Trait,
+            "impl" => ItemKind::Impl,
+            "mod" => ItemKind::Mod,
+            "type" => ItemKind::Type,
+            "const" => ItemKind::Const,
+            "static" => ItemKind::Static,
+            "macro" => ItemKind::Macro,
+            _ => return None
+        };
+        Some(kind)
+    }
+}
+impl fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ItemKind::Fn => "fn",
+            ItemKind::Struct => "struct",
+            ItemKind::Enum => "enum",
+            ItemKind::Union => "union",
+            ItemKind::Trait => "trait",
+            ItemKind::Impl => "impl",
+            ItemKind::Mod => "mod",
+            ItemKind::Type => "type",
+            ItemKind::Const => "const",
+            ItemKind::Static => "static",
+            ItemKind::Macro => "macro"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/*---------------------------------------------------------
+  One item definition
+  - name is matched by Finder, for impls it is the header,
+    e.g., "DirEvent for Finder<T>"
+  - lines are 1-based, end_line holds closing brace or ';'
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub kind: ItemKind,
+    pub name: String,
+    pub path: String,
+    pub line: usize,
+    pub end_line: usize
+}
+
+/*-- all items defined in src, in order of their start --*/
+pub fn items(src: &str) -> Vec<Item> {
+    let code = blank_non_code(src);
+    let mut p = Parser { src: &code, toks: tokens(&code), pos: 0, items: Vec::new() };
+    while p.pos < p.toks.len() {
+        p.parse_block(&[]);
+    }
+    p.items.sort_by_key(|it| it.line);
+    p.items
+}
+
+/*-- src with comments and literals replaced by spaces, newlines kept --*/
+fn blank_non_code(src: &str) -> String {
+    let mut code = src.as_bytes().to_vec();
+    for r in regions(src) {
+        if r.scope != Scope::Code {
+            for b in &mut code[r.start..r.end] {
+                if *b != b'\n' {
+                    *b = b' ';
+                }
+            }
+        }
+    }
+    /* only whole chars were blanked, so code is still UTF-8 */
+    String::from_utf8_lossy(&code).into_owned()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Tok<'a> {
+    text: &'a str,
+    start: usize,
+    line: usize
+}
+
+/*---------------------------------------------------------
+  Split code into identifiers, lifetimes, numbers, and
+  punctuation
+  - "::", "->", and "=>" are single tokens, so '>' in them
+    isn't taken as closing generics
+*/
+fn tokens(code: &str) -> Vec<Tok<'_>> {
+    let b = code.as_bytes();
+    let is_ident = |c: u8| c.is_ascii_alphanumeric() || c == b'_' || c >= 0x80;
+    let mut toks = Vec::<Tok>::new();
+    let mut line = 1usize;
+    let mut i = 0usize;
+    while i < b.len() {
+        let c = b[i];
+        if c == b'\n' {
+            line += 1;
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        if is_ident(c) || (c == b'\'' && i + 1 < b.len() && is_ident(b[i + 1])) {
+            i += 1;
+            while i < b.len() && is_ident(b[i]) {
+                i += 1;
+            }
+        }
+        else if matches!(&b[i..b.len().min(i + 2)], b"::" | b"->" | b"=>") {
+            i += 2;
+        }
+        else {
+            i += 1;
+        }
+        toks.push(Tok { text: &code[start..i], start, line });
+    }
+    toks
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    toks: Vec<Tok<'a>>,
+    pos: usize,
+    items: Vec<Item>
+}
+impl<'a> Parser<'a> {
+    fn text(&self, i: usize) -> &'a str {
+        self.toks.get(i).map_or("", |t| t.text)
+    }
+    fn is_name(&self, i: usize) -> bool {
+        self.text(i).starts_with(|c: char| c.is_alphabetic() || c == '_')
+    }
+    /*-- index after the bracket matching opener at i --*/
+    fn skip_balanced(&self, i: usize) -> usize {
+        let mut depth = 0usize;
+        let mut j = i;
+        while j < self.toks.len() {
+            match self.text(j) {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        return j + 1;
+                    }
+                },
+                _ => {}
+            }
+            j += 1;
+        }
+        self.toks.len()
+    }
+    /*-- first '{' or ';' from i, outside parens and brackets --*/
+    fn find_body(&self, i: usize) -> usize {
+        let mut j = i;
+        while j < self.toks.len() {
+            match self.text(j) {
+                "{" | ";" => return j,
+                "(" | "[" => j = self.skip_balanced(j),
+                _ => j += 1
+            }
+        }
+        self.toks.len()
+    }
+    /*-- index after generics starting with '<' at i --*/
+    fn skip_generics(&self, i: usize) -> usize {
+        if self.text(i) != "<" {
+            return i;
+        }
+        let mut depth = 0usize;
+        let mut j = i;
+        while j < self.toks.len() {
+            match self.text(j) {
+                "<" => depth += 1,
+                ">" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return j + 1;
+                    }
+                },
+                "{" | ";" => return j,
+                _ => {}
+            }
+            j += 1;
+        }
+        j
+    }
+    /*-- attributes before item, e.g., #[derive(Debug)] --*/
+    fn skip_attrs(&self, mut i: usize) -> usize {
+        while self.text(i) == "#" {
+            let j = if self.text(i + 1) == "!" { i + 2 } else { i + 1 };
+            if self.text(j) != "[" {
+                break;
+            }
+            i = self.skip_balanced(j);
+        }
+        i
+    }
+    /*-- visibility and qualifiers before item keyword --*/
+    fn skip_prefix(&self, mut i: usize) -> usize {
+        loop {
+            match self.text(i) {
+                "pub" => {
+                    i += 1;
+                    if self.text(i) == "(" {
+                        i = self.skip_balanced(i);
+                    }
+                },
+                "default" | "unsafe" | "async" | "extern" => i += 1,
+                "const" if matches!(self.text(i + 1), "fn" | "unsafe" | "async" | "extern") => i += 1,
+                _ => return i
+            }
+        }
+    }
+    /*-- source text of tokens first..last, whitespace collapsed --*/
+    fn source(&self, first: usize, last: usize) -> String {
+        if first >= last {
+            return String::new();
+        }
+        let end = self.toks[last - 1].start + self.toks[last - 1].text.len();
+        self.src[self.toks[first].start..end].split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+    fn line(&self, i: usize) -> usize {
+        self.toks.get(i).or(self.toks.last()).map_or(1, |t| t.line)
+    }
+    /*-- item from token first to token before last --*/
+    fn push(&mut self, kind: ItemKind, name: &str, path: &[String], first: usize, last: usize) {
+        let mut full = path.to_vec();
+        full.push(if kind == ItemKind::Impl { format!("<{}>", name) } else { name.to_string() });
+        self.items.push(Item {
+            kind,
+            name: name.to_string(),
+            path: full.join("::"),
+            line: self.line(first),
+            end_line: self.line(last.saturating_sub(1))
+        });
+    }
+    /*-----------------------------------------------------
+      Items of a block, up to and including its closing
+      brace
+      - any brace that doesn't open an item opens a block,
+        whose items are in the same path
+    */
+    fn parse_block(&mut self, path: &[String]) {
+        while self.pos < self.toks.len() {
+            match self.text(self.pos) {
+                "}" => {
+                    self.pos += 1;
+                    return;
+                },
+                "{" => {
+                    self.pos += 1;
+                    self.parse_block(path);
+                },
+                _ => {
+                    if !self.parse_item(path) {
+                        self.pos += 1;
+                    }
+                }
+            }
+        }
+    }
+    /*-- item starting at pos, if any, pos moves past it --*/
+    fn parse_item(&mut self, path: &[String]) -> bool {
+        let start = self.skip_attrs(self.pos);
+        let first = self.skip_prefix(start);
+        let kw = self.text(first);
+        let (kind, name_at) = match kw {
+            "fn" => (ItemKind::Fn, first + 1),
+            "struct" => (ItemKind::Struct, first + 1),
+            "enum" => (ItemKind::Enum, first + 1),
+            "union" if self.is_name(first + 1)
+                && matches!(self.text(first + 2), "{" | "<") => (ItemKind::Union, first + 1),
+            "trait" => (ItemKind::Trait, first + 1),
+            "mod" => (ItemKind::Mod, first + 1),
+            "type" => (ItemKind::Type, first + 1),
+            "const" => (ItemKind::Const, first + 1),
+            "static" if self.text(first + 1) == "mut" => (ItemKind::Static, first + 2),
+            "static" => (ItemKind::Static, first + 1),
+            "macro_rules" if self.text(first + 1) == "!" => (ItemKind::Macro, first + 2),
+            "impl" => return self.parse_impl(path, start, first),
+            _ => return false
+        };
+        if !self.is_name(name_at) {
+            return false;
+        }
+        let name = self.text(name_at).to_string();
+        let body = self.find_body(name_at + 1);
+        match (kind, self.text(body)) {
+            (ItemKind::Fn | ItemKind::Trait | ItemKind::Mod, "{") => {
+                let mut inner = path.to_vec();
+                inner.push(name.clone());
+                self.pos = body + 1;
+                self.parse_block(&inner);
+            },
+            (ItemKind::Macro, _) => {
+                let open = name_at + 1;
+                self.pos = self.skip_balanced(open);
+                if self.text(self.pos) == ";" {
+                    self.pos += 1;
+                }
+            },
+            (_, "{") if !matches!(kind, ItemKind::Const | ItemKind::Static) => {
+                self.pos = self.skip_balanced(body);
+            },
+            _ => {
+                /* ends at ';', braces in initializers are skipped */
+                let mut j = name_at + 1;
+                while j < self.toks.len() && self.text(j) != ";" {
+                    j = if matches!(self.text(j), "(" | "[" | "{") { self.skip_balanced(j) } else { j + 1 };
+                }
+                self.pos = (j + 1).min(self.toks.len());
+            }
+        }
+        self.push(kind, &name, path, start, self.pos);
+        true
+    }
+    /*-----------------------------------------------------
+      impl block, named by its header, e.g., "Trait for
+      Type<T>", items in it get path of Type
+    */
+    fn parse_impl(&mut self, path: &[String], start: usize, first: usize) -> bool {
+        let hdr = self.skip_generics(first + 1);
+        let mut end = hdr;
+        while end < self.toks.len() && !matches!(self.text(end), "{" | ";" | "where") {
+            end = match self.text(end) {
+                "(" | "[" => self.skip_balanced(end),
+                _ => end + 1
+            };
+        }
+        let body = self.find_body(end);
+        if self.text(body) != "{" || end == hdr {
+            return false;
+        }
+        let name = self.source(hdr, end);
+        /* self type is last name outside generics, after "for" if any */
+        let ty_start = (hdr..end).rev().find(|&i| self.text(i) == "for").map_or(hdr, |i| i + 1);
+        let mut depth = 0i32;
+        let mut ty = "";
+        for i in ty_start..end {
+            match self.text(i) {
+                "<" => depth += 1,
+                ">" => depth -= 1,
+                _ if depth == 0 && self.is_name(i) && !matches!(self.text(i), "dyn" | "mut") => {
+                    ty = self.text(i);
+                },
+                _ => {}
+            }
+        }
+        let mut inner = path.to_vec();
+        inner.push(ty.to_string());
+        self.pos = body + 1;
+        self.parse_block(&inner);
+        self.push(ItemKind::Impl, &name, path, start, self.pos);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    /* (kind, path, line, end_line) of each item */
+    fn find(src: &str) -> Vec<(ItemKind, String, usize, usize)> {
+        items(src).into_iter().map(|it| (it.kind, it.path, it.line, it.end_line)).collect()
+    }
+    #[test]
+    fn items_found() {
+        let src = r#"
+/// doc, with fn in comment
+#[derive(Debug)]
+pub(crate) struct Finder<T> { out: T }
+impl<T: SearchEvent> DirEvent for Finder<T> {
+    fn do_dir(&mut self, d: &Path) {
+        let s = "} fn fake() {";
+        let c = '}';
+    }
+}
+pub trait SearchEvent {
+    fn set_file(&mut self);
+    const N: usize = { 3 };
+}
+mod tests {
+    use super::*;
+    pub async unsafe fn helper<'a>(x: &'a str) -> impl Fn() -> u8 where T: Copy {
+        struct Local;
+    }
+    static mut COUNT: u32 = 0;
+    type Out = Vec<u8>;
+    enum E { A, B }
+    macro_rules! m { () => {} }
+}
+"#;
+        assert_eq!(find(src), vec![
+            (ItemKind::Struct, "Finder".to_string(), 4, 4),
+            (ItemKind::Impl, "<DirEvent for Finder<T>>".to_string(), 5, 10),
+            (ItemKind::Fn, "Finder::do_dir".to_string(), 6, 9),
+            (ItemKind::Trait, "SearchEvent".to_string(), 11, 14),
+            (ItemKind::Fn, "SearchEvent::set_file".to_string(), 12, 12),
+            (ItemKind::Const, "SearchEvent::N".to_string(), 13, 13),
+            (ItemKind::Mod, "tests".to_string(), 15, 24),
+            (ItemKind::Fn, "tests::helper".to_string(), 17, 19),
+            (ItemKind::Struct, "tests::helper::Local".to_string(), 18, 18),
+            (ItemKind::Static, "tests::COUNT".to_string(), 20, 20),
+            (ItemKind::Type, "tests::Out".to_string(), 21, 21),
+            (ItemKind::Enum, "tests::E".to_string(), 22, 22),
+            (ItemKind::Macro, "tests::m".to_string(), 23, 23)
+        ]);
+        let it = &items(src)[1];
+        assert_eq!(it.name, "DirEvent for Finder<T>");
+    }
+    #[test]
+    fn not_items() {
+        /* fn pointer types, unsafe blocks, and variables named union */
+        let src = "fn f() { let g: fn(u8) -> u8 = h; unsafe { x() }; let union = 1; }\nimpl Finder { }";
+        assert_eq!(find(src), vec![
+            (ItemKind::Fn, "f".to_string(), 1, 1),
+            (ItemKind::Impl, "<Finder>".to_string(), 2, 2)
+        ]);
+        assert_eq!(ItemKind::parse("trait"), Some(ItemKind::Trait));
+        assert_eq!(ItemKind::Macro.to_string(), "macro");
+    }
+}
@@ -8,13 +8,85 @@
 use std::path::{Path, PathBuf};
 use std::fs::{OpenOptions};
 use std::io::*;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Instant;
 use dir_nav::{DirEvent};
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
+
+pub mod query;
+pub mod stream;
+pub mod encoding;
+pub mod replace;
+pub mod fuzzy;
+pub mod terms;
+pub mod capture;
+pub mod compress;
+pub mod matcher;
+pub mod result;
+pub mod pool;
+pub mod scope;
+pub mod items;
+pub use query::{Query, QueryError};
+pub use replace::Replaced;
+pub use terms::Terms;
+pub use capture::{Capture, CaptureSet, CaptureTally};
+pub use matcher::Matcher;
+pub use pool::{WorkPool, Recorder};
+pub use scope::Scope;
+pub use items::{Item, ItemKind};
+pub use result::{FileResult, FileStatus, FileError, FILE_RESULT_VERSION};
+use matcher::{LiteralMatcher, RegexMatcher, FuzzyMatcher, TermsMatcher};
+use stream::{LineStream, ContextCollector, MAX_LISTED};
+use encoding::{DecodeReader, select_encoding};
+use encoding_rs::Encoding;
+
+/*---------------------------------------------------------
+  Match holds the location of one match in a searched file
+  - line and col are 1-based, col counts chars, not bytes
+  - offset is byte offset of match from start of file text,
+    after decoding to UTF-8
+  - end_line is the line holding the last char of the match,
+    greater than line only for multi-line regex matches
+  - text is the full line containing the match, or all the
+    lines it spans, joined with '\n'
+  - matched is the text matched
+  - distance is edit distance of matched from search text,
+    for fuzzy search only
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Match {
+    pub line: usize,
+    pub end_line: usize,
+    pub col: usize,
+    pub offset: usize,
+    pub text: String,
+    pub matched: String,
+    pub distance: Option<usize>
+}
+
+/*---------------------------------------------------------
+  ContextLine is one line of a ContextBlock
+  - is_match is true for lines holding matches, false for
+    leading and trailing context lines
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContextLine {
+    pub line: usize,
+    pub text: String,
+    pub is_match: bool
+}
+/*-- contiguous lines around one or more nearby matches --*/
+pub type ContextBlock = Vec<ContextLine>;
 
 pub trait SearchEvent {
     fn new() -> Self;
     fn set_dir(&mut self, dir: &Path);
-    fn set_file(&mut self, rslt:(&Path, bool, &str));
+    fn set_file(&mut self, rslt: &FileResult);
+    /*-- sent before set_file with Error status, default ignores it --*/
+    fn set_error(&mut self, _err: &FileError) {}
 }
 
 /*---------------------------------------------------------
@@ -26,6 +98,113 @@ pub trait SearchEvent {
 //     fn do_file(&mut self, f: &Path);
 // }
 
+/*---------------------------------------------------------
+  Boundary selects which matches are accepted based on the
+  chars on either side of the match
+  - Word:  neighbors may not be alphanumeric
+  - Ident: neighbors may not be alphanumeric or '_', as for
+           Rust identifiers
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Boundary {
+    #[default]
+    None,
+    Word,
+    Ident
+}
+impl Boundary {
+    fn is_word_char(&self, c: char) -> bool {
+        match self {
+            Boundary::None => false,
+            Boundary::Word => c.is_alphanumeric(),
+            Boundary::Ident => c.is_alphanumeric() || c == '_'
+        }
+    }
+    /*-- does span (start, end) of buffer fall on boundaries? --*/
+    pub fn accepts(&self, buffer: &str, start: usize, end: usize) -> bool {
+        let before = buffer[..start].chars().next_back();
+        let after = buffer[end..].chars().next();
+        !before.is_some_and(|c| self.is_word_char(c))
+            && !after.is_some_and(|c| self.is_word_char(c))
+    }
+    /*-- as accepts, for span of raw bytes, invalid UTF-8 is not a word char --*/
+    pub fn accepts_bytes(&self, bytes: &[u8], start: usize, end: usize) -> bool {
+        let before = String::from_utf8_lossy(&bytes[start.saturating_sub(4)..start])
+            .chars().next_back();
+        let after = String::from_utf8_lossy(&bytes[end..(end + 4).min(bytes.len())])
+            .chars().next();
+        !before.is_some_and(|c| self.is_word_char(c))
+            && !after.is_some_and(|c| self.is_word_char(c))
+    }
+}
+
+/*---------------------------------------------------------
+  BinaryPolicy selects handling of binary files
+  - Skip:  don't search, count in statistics
+  - Match: search the bytes, report only "binary file matches"
+  - Text:  search and report as if text
+  Invalid UTF-8 is replaced with U+FFFD for Match and Text,
+  so text patterns still match in the valid parts.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BinaryPolicy {
+    #[default]
+    Skip,
+    Match,
+    Text
+}
+
+/*---------------------------------------------------------
+  NameMode selects what Finder searches
+  - Contents: file contents, the default
+  - Name:     file name, files are not opened
+  - Path:     dir and file name, as sent by DirNav, with '/'
+              separators
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameMode {
+    #[default]
+    Contents,
+    Name,
+    Path
+}
+
+/*---------------------------------------------------------
+  Limit that stopped a search
+  - Files:   number of files searched reached the maximum
+  - Matches: number of matches reached the maximum, the
+             file with the last match is reported whole
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Files(usize),
+    Matches(usize)
+}
+
+/*-- number of leading bytes examined by is_binary --*/
+pub const BINARY_BLOCK: usize = 8 * 1024;
+
+/*-----------------------------------------------------------
+  Is block binary?  Yes if it holds a NUL byte, or invalid
+  UTF-8 along with control chars other than whitespace and
+  escape.  Invalid UTF-8 without them is legacy text, see
+  encoding::LEGACY.  A char cut off at end of block is not
+  invalid.
+*/
+pub fn is_binary(block: &[u8]) -> bool {
+    if block.contains(&0u8) {
+        return true;
+    }
+    let invalid = match std::str::from_utf8(block) {
+        Ok(_) => false,
+        Err(e) => e.error_len().is_some()
+    };
+    invalid && block.iter().any(|&b| is_control(b))
+}
+fn is_control(b: u8) -> bool {
+    (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0C | 0x1B)) || b == 0x7F
+}
+
 /*---------------------------------------------------------
   Finder implements finding text strings in files
 */
@@ -33,79 +212,1414 @@ pub trait SearchEvent {
 pub struct Finder<T: SearchEvent> {
     dir : PathBuf,
     srch_txt : String,
+    matcher : Option<Arc<dyn Matcher>>,
+    custom : bool,
     regx : Option<Regex>,
     reg_txt : String,
+    terms : Option<Terms>,
+    term_list : Vec<String>,
+    term_counts : Vec<usize>,
+    query : Option<Query>,
+    qry_txt : String,
+    qry_lines : bool,
+    flags : RegexFlags,
+    ignore_case : bool,
+    boundary : Boundary,
+    fuzzy : Option<usize>,
+    before : usize,
+    after : usize,
+    count_only : bool,
+    invert : bool,
+    decompress : bool,
+    extract : bool,
+    aggregate : bool,
+    cap_sets : Vec<CaptureSet>,
+    tally : CaptureTally,
+    buf_size : usize,
+    binary : BinaryPolicy,
+    names : NameMode,
+    scopes : Vec<Scope>,
+    scoped : bool,
+    item_kinds : Option<Vec<ItemKind>>,
+    found_items : Vec<Item>,
+    note : String,
+    bin_match : bool,
+    replaced : Option<Replaced>,
+    encoding : Option<&'static Encoding>,
+    replace : Option<String>,
+    dry_run : bool,
+    backup : String,
     out : T,
-    num_found : usize
+    started : Option<Instant>,
+    file_bytes : u64,
+    num_found : usize,
+    num_occurs : usize,
+    num_binary : usize,
+    num_errors : usize,
+    num_replaced : usize,
+    num_large : usize,
+    num_files : usize,
+    num_searched : usize,
+    max_size : u64,
+    max_files : usize,
+    max_matches : usize,
+    limit : Option<Limit>,
+    threads : usize,
+    occurs_done : Arc<AtomicUsize>,
+    searched_done : Arc<AtomicUsize>,
+    pool : Option<WorkPool>,
+    pending : VecDeque<Pending>
+}
+/*-- dir or file waiting to be sent to output, in traversal order --*/
+#[derive(Debug)]
+enum Pending {
+    Dir(PathBuf),
+    File(PathBuf, Receiver<Finder<Recorder>>)
 }
 impl<T: SearchEvent> DirEvent for Finder<T> {
     fn new() -> Self {
         Self {
             dir: PathBuf::from("."),
             srch_txt: String::new(),
+            matcher: None,
+            custom: false,
             regx: None,
             reg_txt: String::new(),
+            terms: None,
+            term_list: Vec::new(),
+            term_counts: Vec::new(),
+            query: None,
+            qry_txt: String::new(),
+            qry_lines: false,
+            flags: RegexFlags::default(),
+            ignore_case: false,
+            boundary: Boundary::None,
+            fuzzy: None,
+            before: 0usize,
+            after: 0usize,
+            count_only: false,
+            invert: false,
+            decompress: false,
+            extract: false,
+            aggregate: false,
+            cap_sets: Vec::new(),
+            tally: CaptureTally::new(),
+            buf_size: 0usize,
+            binary: BinaryPolicy::Skip,
+            names: NameMode::Contents,
+            scopes: Vec::new(),
+            scoped: false,
+            item_kinds: None,
+            found_items: Vec::new(),
+            note: String::new(),
+            bin_match: false,
+            replaced: None,
+            encoding: None,
+            replace: None,
+            dry_run: false,
+            backup: String::new(),
             out: T::new(),
-            num_found : 0usize
+            started: None,
+            file_bytes: 0u64,
+            num_found : 0usize,
+            num_occurs : 0usize,
+            num_binary : 0usize,
+            num_errors : 0usize,
+            num_replaced : 0usize,
+            num_large : 0usize,
+            num_files : 0usize,
+            num_searched : 0usize,
+            max_size: 0u64,
+            max_files: 0usize,
+            max_matches: 0usize,
+            limit: None,
+            threads: 1usize,
+            occurs_done: Arc::new(AtomicUsize::new(0)),
+            searched_done: Arc::new(AtomicUsize::new(0)),
+            pool: None,
+            pending: VecDeque::new()
         }
     }
     /*-- called by DirNav --*/
     fn do_dir(&mut self, dir: &Path) {
         self.dir = dir.to_path_buf();
-        self.out.set_dir(dir);
+        if self.pending.is_empty() {
+            self.out.set_dir(dir);
+        }
+        else {
+            self.pending.push_back(Pending::Dir(self.dir.clone()));
+        }
     }
     /*-- called by DirNav --*/
     fn do_file(&mut self, file_name: &Path) {
+        if self.is_done() {
+            return;
+        }
+        self.num_files += 1;
+        if self.threads > 1 {
+            self.submit_file(file_name);
+        }
+        else {
+            self.search_file(file_name);
+        }
+        self.check_limits();
+    }
+    /*-- called by DirNav, stops visiting once a limit is reached --*/
+    fn is_done(&self) -> bool {
+        self.limit.is_some()
+    }
+}
+impl<T: SearchEvent> Finder<T> {
+    /*-- search one file, sending results to output --*/
+    fn search_file(&mut self, file_name: &Path) {
+        self.started = Some(Instant::now());
+        self.file_bytes = 0;
+        if self.is_backup(file_name) {
+            self.report_status(file_name, FileStatus::Skipped, "backup file");
+            return;
+        }
+        self.term_counts.clear();
+        self.cap_sets.clear();
+        self.found_items.clear();
+        self.note.clear();
+        self.bin_match = false;
+        self.replaced = None;
         let path = Path::new(&self.dir);
         let path = path.join(file_name);
-        let rslt = OpenOptions::new().read(true).open(path);
-        if rslt.is_err() {
-            self.out.set_file((file_name,false,"can't open file"));
+        if self.names != NameMode::Contents {
+            self.search_name(file_name, &path);
             return;
         }
-        let mut file = rslt.unwrap();
-        
-        let mut buffer = String::new();
-        let rslt = file.read_to_string(&mut buffer);
-        if rslt.is_ok() {
-            if self.reg_txt.is_empty() {  /* text search */
-                let found:bool = buffer.contains(&self.srch_txt);
-                if found {
-                    self.num_found += 1;
+        let rust_src = file_name.extension().is_some_and(|ext| ext == "rs");
+        if self.item_kinds.is_some() && !rust_src {
+            self.report_status(file_name, FileStatus::Skipped, "not Rust source");
+            return;
+        }
+        self.scoped = !self.scopes.is_empty() && rust_src;
+        let rslt = OpenOptions::new().read(true).open(&path);
+        let file = match rslt {
+            Ok(file) => file,
+            Err(e) => {
+                self.report_error(file_name, "open", &e);
+                return;
+            }
+        };
+        self.file_bytes = file.metadata().map_or(0, |md| md.len());
+        if self.max_size > 0 && self.file_bytes > self.max_size {
+            self.num_large += 1;
+            let msg = format!("larger than {} bytes", self.max_size);
+            self.report_status(file_name, FileStatus::Skipped, &msg);
+            return;
+        }
+        let (mut file, compressed): (Box<dyn Read>, bool) = 
+            if self.decompress {
+                let cap = if self.max_size > 0 { self.max_size } else { compress::MAX_DECOMPRESSED };
+                match compress::reader(file, cap) {
+                    Ok((rdr, comp)) => (rdr, comp.is_some()),
+                    Err(e) => {
+                        self.report_error(file_name, "decompress", &e);
+                        return;
+                    }
                 }
-                self.out.set_file((file_name,found,&self.srch_txt));
-            }
-            else {  /* regex match */
-                let re_opt = &mut self.regx;
-                if let Some(re) = re_opt {
-                    let found:bool = re.is_match(buffer.as_str());
-                    if found {
-                        self.num_found += 1;
+            }
+            else {
+                (Box::new(file), false)
+            };
+        if self.buf_size > 0 {
+            self.stream_file(file_name, file);
+            return;
+        }
+        
+        let mut bytes = Vec::<u8>::new();
+        if let Err(e) = file.read_to_end(&mut bytes) {
+            self.report_read_error(file_name, &e);
+            return;
+        }
+        let head = &bytes[..bytes.len().min(BINARY_BLOCK)];
+        let mut enc = select_encoding(head, self.encoding);
+        let utf8_bom = Encoding::for_bom(head).is_some_and(|(e, _)| e == encoding_rs::UTF_8);
+        let binary = enc.is_none() && is_binary(head);
+        if self.raw_search(binary) {
+            let count = self.count_bytes(&bytes);
+            self.report_binary(file_name, count > 0, count);
+            return;
+        }
+        let buffer = 
+            if let Some(enc) = enc {
+                encoding::decode(&bytes, enc)
+            }
+            else if binary {
+                String::from_utf8_lossy(&bytes).into_owned()
+            }
+            else {
+                match String::from_utf8(bytes) {
+                    Ok(text) => text,
+                    Err(e) => {  /* legacy text, decoded without loss */
+                        enc = Some(encoding::LEGACY);
+                        encoding::decode(e.as_bytes(), encoding::LEGACY)
                     }
-                    self.out.set_file((file_name,found,&self.reg_txt));
                 }
+            };
+        if binary && self.binary == BinaryPolicy::Skip {
+            self.num_binary += 1;
+            self.report_status(file_name, FileStatus::Skipped, "binary file");
+            return;
+        }
+        if self.item_kinds.is_some() {
+            self.search_items(file_name, &buffer);
+            return;
+        }
+        let (found, spans) = self.search(&buffer);
+        self.tally_terms(&buffer, &spans);
+        self.collect_captures(&buffer, &spans, 1);
+        if binary && self.binary == BinaryPolicy::Match {
+            self.report_binary(file_name, found, spans.len());
+            return;
+        }
+        let mut matches = Vec::<Match>::new();
+        let mut blocks = Vec::<ContextBlock>::new();
+        if found && !self.count_only && !self.invert {
+            matches = line_matches(&buffer, &spans);
+            self.set_distances(&mut matches);
+            if self.before > 0 || self.after > 0 {
+                blocks = context_blocks(
+                    &buffer, &matches, self.before, self.after
+                );
             }
         }
+        if self.replace.is_some() && !spans.is_empty() {
+            let rslt = 
+                if binary {
+                    Replaced::Failed("binary file".to_string())
+                }
+                else if compressed {
+                    Replaced::Failed("compressed file".to_string())
+                }
+                else {
+                    self.replace_file(&path, file_name, &buffer, &spans, enc, utf8_bom)
+                };
+            self.replaced = Some(rslt);
+        }
+        self.report(file_name, found, spans.len(), matches, blocks);
     }
 }
 impl<T: SearchEvent> Finder<T> {
     /*-- called by Executive based on Cmdln opts --*/
     pub fn set_txt(&mut self, srctxt: &str) {
         self.srch_txt = srctxt.to_string();
+        self.update_matcher();
     }
-    /*-- called by Executive based on Cmdln opts --*/
-    pub fn set_regex(&mut self, regex: &str) {
+    /*-----------------------------------------------------
+      called by Executive based on Cmdln opts
+      - on error regex is left unset, so validate fails
+    */
+    pub fn set_regex(&mut self, regex: &str) -> std::result::Result<(), regex::Error> {
         self.reg_txt = regex.to_string();
-        let re = Regex::new(regex).expect("unvalid regex");
+        self.regx = None;
+        self.update_matcher();
+        let re = build_regex(regex, &self.flags, self.ignore_case)?;
         self.regx = Some(re);
+        self.update_matcher();
+        Ok(())
+    }
+    /*-----------------------------------------------------
+      error from set_regex, explained
+      - with Unicode off, says how to match whole chars
+    */
+    pub fn regex_error_msg(&self, e: &regex::Error) -> String {
+        let msg = e.to_string();
+        if !self.flags.unicode && msg.contains("invalid UTF-8") {
+            return format!(
+                "{}\nwith Unicode off, . and negated classes can match part of a char,\n\
+                 use (?u:.) or [[:ascii:]] instead", msg
+            );
+        }
+        msg
+    }
+    /*-----------------------------------------------------
+      called by Executive based on Cmdln opts
+      - many literal terms found in one pass, replacing
+        search text
+      - see terms.rs
+    */
+    pub fn set_terms(&mut self, terms: &[String]) -> std::result::Result<(), String> {
+        self.term_list = terms.to_vec();
+        self.terms = None;
+        self.update_matcher();
+        if terms.is_empty() {
+            return Ok(());
+        }
+        let t = Terms::new(terms, self.ignore_case)?;
+        self.terms = Some(t);
+        self.update_matcher();
+        Ok(())
+    }
+    /*-----------------------------------------------------
+      called by Executive based on Cmdln opts
+      - boolean query replaces search text and regex
+      - see query.rs for syntax
+      - on error query is left unset, so validate fails
+    */
+    pub fn set_query(&mut self, qry: &str) -> std::result::Result<(), QueryError> {
+        self.qry_txt = qry.to_string();
+        self.query = None;
+        let mut q = Query::with_flags(qry, self.ignore_case, &self.flags)?;
+        q.set_boundary(self.boundary);
+        self.query = Some(q);
+        Ok(())
+    }
+    /*-- called by Executive, evaluate query on each line, not file --*/
+    pub fn set_query_lines(&mut self, ql: bool) {
+        self.qry_lines = ql;
+    }
+    /*-----------------------------------------------------
+      called by Executive, flags used to build regex, and
+      regex terms of query
+      - streaming search is line by line, so matches never
+        span lines there, even with m and s flags
+    */
+    pub fn set_regex_flags(&mut self, flags: RegexFlags) {
+        self.flags = flags;
+        if !self.reg_txt.is_empty() {
+            let reg = self.reg_txt.clone();
+            let _ = self.set_regex(&reg);  /* errors found by validate */
+        }
+        if !self.qry_txt.is_empty() {
+            let qry = self.qry_txt.clone();
+            let _ = self.set_query(&qry);  /* errors found by validate */
+        }
+    }
+    /*-- called by Executive, applies to text, regex, and query --*/
+    pub fn set_ignore_case(&mut self, ic: bool) {
+        self.ignore_case = ic;
+        let txt = self.srch_txt.clone();
+        self.set_txt(&txt);
+        if !self.reg_txt.is_empty() {
+            let reg = self.reg_txt.clone();
+            let _ = self.set_regex(&reg);  /* errors found by validate */
+        }
+        if !self.qry_txt.is_empty() {
+            let qry = self.qry_txt.clone();
+            let _ = self.set_query(&qry);  /* errors found by validate */
+        }
+        if self.terms.is_some() {
+            let terms = self.term_list.clone();
+            let _ = self.set_terms(&terms);
+        }
+    }
+    /*-- called by Executive, accept only matches on boundaries --*/
+    pub fn set_boundary(&mut self, b: Boundary) {
+        self.boundary = b;
+        if let Some(q) = &mut self.query {
+            q.set_boundary(b);
+        }
+    }
+    /*-----------------------------------------------------
+      called by Executive, find text with at most k edits
+      - None restores exact matching
+      - see fuzzy.rs
+    */
+    pub fn set_fuzzy(&mut self, k: Option<usize>) {
+        self.fuzzy = k;
+        self.update_matcher();
+    }
+    /*-----------------------------------------------------
+      called by clients with their own matching strategy
+      - replaces search text, regex, and terms, a query is
+        still used instead, if set
+      - None restores the matcher built from search options
+    */
+    pub fn set_matcher(&mut self, m: Option<Box<dyn Matcher>>) {
+        self.custom = m.is_some();
+        self.matcher = m.map(Arc::from);
+        self.update_matcher();
+    }
+    /*-----------------------------------------------------
+      Build matcher from search options, unless client
+      supplied one
+      - terms, then regex, then fuzzy or literal text
+    */
+    fn update_matcher(&mut self) {
+        if self.custom {
+            return;
+        }
+        self.matcher =
+            if let Some(t) = &self.terms {
+                Some(Arc::new(TermsMatcher::new(t.clone())))
+            }
+            else if !self.reg_txt.is_empty() {
+                self.regx.as_ref().map(|re| {
+                    Arc::new(RegexMatcher::new(re.clone())) as Arc<dyn Matcher>
+                })
+            }
+            else if self.srch_txt.is_empty() {
+                None
+            }
+            else if let Some(k) = self.fuzzy {
+                Some(Arc::new(FuzzyMatcher::new(&self.srch_txt, k, self.ignore_case)))
+            }
+            else {
+                Some(Arc::new(LiteralMatcher::new(&self.srch_txt, self.ignore_case)))
+            };
+    }
+    /*-- called by Executive, lines shown before and after matches --*/
+    pub fn set_context(&mut self, before: usize, after: usize) {
+        self.before = before;
+        self.after = after;
+    }
+    /*-- called by Executive, report only counts, not lines --*/
+    pub fn set_count_only(&mut self, c: bool) {
+        self.count_only = c;
+    }
+    /*-- called by Executive, send regex captures to output, not lines --*/
+    pub fn set_extract(&mut self, ex: bool) {
+        self.extract = ex;
+    }
+    /*-- called by Executive, count distinct captured values --*/
+    pub fn set_aggregate(&mut self, agg: bool) {
+        self.aggregate = agg;
+    }
+    /*-- distinct captured values, with counts, from all files --*/
+    pub fn get_captures(&self) -> &CaptureTally {
+        &self.tally
+    }
+    /*-----------------------------------------------------
+      called by Executive, search decompressed text of gzip,
+      xz, and zstd files
+      - results are reported under the compressed file name
+      - files decompressing to more than max size bytes, or
+        compress::MAX_DECOMPRESSED if none, are skipped
+      - see compress.rs
+    */
+    pub fn set_decompress(&mut self, dc: bool) {
+        self.decompress = dc;
+    }
+    /*-- called by Executive, report files that don't match instead --*/
+    pub fn set_invert(&mut self, inv: bool) {
+        self.invert = inv;
+    }
+    pub fn get_invert(&self) -> bool {
+        self.invert
+    }
+    /*-- called by Executive before traversal, is search usable? --*/
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if !self.reg_txt.is_empty() && self.regx.is_none() {
+            if let Err(e) = build_regex(&self.reg_txt, &self.flags, self.ignore_case) {
+                return Err(format!("invalid regex:\n{}", self.regex_error_msg(&e)));
+            }
+            return Err(format!("regex {:?} not compiled", self.reg_txt));
+        }
+        if !self.qry_txt.is_empty() && self.query.is_none() {
+            if let Err(e) = Query::with_flags(&self.qry_txt, self.ignore_case, &self.flags) {
+                return Err(format!("invalid query: {}", e));
+            }
+            return Err(format!("query {:?} not parsed", self.qry_txt));
+        }
+        if self.query.is_some() && self.buf_size > 0 && !self.qry_lines {
+            return Err(
+                "streaming can evaluate a query only on each line, not on whole files"
+                .to_string()
+            );
+        }
+        if self.terms.is_some() && (!self.reg_txt.is_empty() || self.query.is_some()) {
+            return Err("search terms can't be combined with regex or query".to_string());
+        }
+        if (self.extract || self.aggregate) 
+            && (self.reg_txt.is_empty() || self.query.is_some() || self.terms.is_some()) {
+            return Err("capture extraction needs a regex".to_string());
+        }
+        if let Some(k) = self.fuzzy {
+            if !self.reg_txt.is_empty() || self.query.is_some() || self.terms.is_some() {
+                return Err("fuzzy matching applies to search text, not regex, query, or terms".to_string());
+            }
+            if k >= self.srch_txt.chars().count() {
+                return Err(format!(
+                    "fuzzy distance {} must be less than length of {:?}", k, self.srch_txt
+                ));
+            }
+        }
+        if self.item_kinds.is_some() 
+            && (self.buf_size > 0 || self.names != NameMode::Contents 
+                || !self.scopes.is_empty() || self.extract || self.aggregate
+                || self.replace.is_some()) {
+            return Err(
+                "item search can't use streaming, file names, scopes, captures, or replace"
+                .to_string()
+            );
+        }
+        if !self.scopes.is_empty() {
+            if self.query.is_some() {
+                return Err("scopes apply to text, regex, or terms, not query".to_string());
+            }
+            if self.buf_size > 0 {
+                return Err("scopes need whole files, can't stream them".to_string());
+            }
+            if self.names != NameMode::Contents {
+                return Err("scopes can't be used with file name search".to_string());
+            }
+        }
+        if self.replace.is_some() {
+            if self.invert {
+                return Err("replace can't be used with inverted search".to_string());
+            }
+            if self.buf_size > 0 {
+                return Err("replace reads whole files, can't stream them".to_string());
+            }
+            if self.is_empty_search() {
+                return Err("replace needs search text, regex, or query".to_string());
+            }
+            if self.names != NameMode::Contents {
+                return Err("replace can't be used with file name search".to_string());
+            }
+            if self.max_matches > 0 && self.threads > 1 {
+                return Err("replace with a match limit needs one thread".to_string());
+            }
+        }
+        if self.max_matches > 0 && self.invert {
+            return Err("match limit can't be used with inverted search".to_string());
+        }
+        Ok(())
+    }
+    /*-----------------------------------------------------
+      called by Executive, read files in chunks of size
+      bytes, searching line by line
+      - size 0 reads whole files, the default
+      - queries must be evaluated on each line, see
+        set_query_lines, validate rejects file queries
+    */
+    pub fn set_buffer_size(&mut self, size: usize) {
+        self.buf_size = size;
+    }
+    /*-- called by Executive, search file contents, names, or paths --*/
+    pub fn set_name_mode(&mut self, nm: NameMode) {
+        self.names = nm;
+    }
+    /*-----------------------------------------------------
+      called by Executive, match only in these scopes of
+      Rust sources, see scope.rs
+      - files without the rs extension are searched whole
+      - empty scopes searches all of each file
+    */
+    pub fn set_scopes(&mut self, scopes: &[Scope]) {
+        self.scopes = scopes.to_vec();
+    }
+    /*-----------------------------------------------------
+      called by Executive, search Rust items, not text,
+      see items.rs
+      - search text, regex, or query is matched against
+        item names, an empty search matches all items
+      - empty kinds matches items of any kind
+      - None searches text
+    */
+    pub fn set_items(&mut self, kinds: Option<&[ItemKind]>) {
+        self.item_kinds = kinds.map(|k| k.to_vec());
+    }
+    /*-- called by Executive, how binary files are handled --*/
+    pub fn set_binary_policy(&mut self, bp: BinaryPolicy) {
+        self.binary = bp;
+    }
+    /*-----------------------------------------------------
+      called by Executive, decode all files with encoding
+      label, e.g., "windows-1252" or "utf-16le"
+      - empty label restores detection from byte order mark
+    */
+    pub fn set_encoding(&mut self, label: &str) -> std::result::Result<(), String> {
+        if label.is_empty() {
+            self.encoding = None;
+            return Ok(());
+        }
+        match encoding::encoding_for(label) {
+            Some(enc) => {
+                self.encoding = Some(enc);
+                Ok(())
+            },
+            None => Err(format!("unknown encoding {:?}", label))
+        }
+    }
+    /*-----------------------------------------------------
+      called by Executive, rewrite matches with rep
+      - None turns replace mode off
+      - see replace.rs for capture group syntax
+    */
+    pub fn set_replace(&mut self, rep: Option<&str>) {
+        self.replace = rep.map(|r| r.to_string());
+    }
+    /*-- called by Executive, show diffs without writing files --*/
+    pub fn set_dry_run(&mut self, dr: bool) {
+        self.dry_run = dr;
+    }
+    /*-- called by Executive, copy originals to name + suffix --*/
+    pub fn set_backup(&mut self, suffix: &str) {
+        self.backup = suffix.to_string();
     }
     /*-- called by Executive to config GenOut --*/
     pub fn get_app(&mut self) -> &mut T {
         &mut self.out
     }
+    /*-----------------------------------------------------
+      called by Executive, number of threads searching files
+      - more than one searches files on a WorkPool, sending
+        results to output in traversal order
+      - call finish after DirNav::visit to send the last
+        results and stop the threads
+    */
+    pub fn set_threads(&mut self, n: usize) {
+        self.threads = n.max(1);
+    }
+    pub fn get_threads(&self) -> usize {
+        self.threads
+    }
+    /*-- send results still pending to output, stop threads --*/
+    pub fn finish(&mut self) {
+        self.flush(true);
+        self.pool = None;
+        self.check_limits();
+    }
+    /*-----------------------------------------------------
+      called by Executive, skip files larger than size bytes,
+      0 for no limit
+      - with decompress, also caps decompressed size
+    */
+    pub fn set_max_size(&mut self, size: u64) {
+        self.max_size = size;
+    }
+    /*-- called by Executive, stop after n files searched, 0 for no limit --*/
+    pub fn set_max_files(&mut self, n: usize) {
+        self.max_files = n;
+    }
+    /*-- called by Executive, stop after n matches, 0 for no limit, not with invert --*/
+    pub fn set_max_matches(&mut self, n: usize) {
+        self.max_matches = n;
+    }
+    /*-- limit that stopped search, if any --*/
+    pub fn get_limit(&self) -> Option<Limit> {
+        self.limit
+    }
+    /*-- number of files searched, not counting skipped files or errors --*/
+    pub fn get_num_searched(&self) -> usize {
+        self.num_searched
+    }
+    /*-- number of files skipped as larger than max size --*/
+    pub fn get_num_large(&self) -> usize {
+        self.num_large
+    }
+    /*-- number of files with matches, or without, if inverted --*/
     pub fn get_num_found(&self) -> usize {
         self.num_found
     }
+    /*-- number of matches in all files --*/
+    pub fn get_num_occurs(&self) -> usize {
+        self.num_occurs
+    }
+    /*-- number of binary files skipped --*/
+    pub fn get_num_binary(&self) -> usize {
+        self.num_binary
+    }
+    /*-- number of files that could not be opened or read --*/
+    pub fn get_num_errors(&self) -> usize {
+        self.num_errors
+    }
+    /*-- number of files rewritten by replace mode --*/
+    pub fn get_num_replaced(&self) -> usize {
+        self.num_replaced
+    }
+    /*-- don't search backups written by earlier files --*/
+    fn is_backup(&self, file_name: &Path) -> bool {
+        self.replace.is_some() && !self.backup.is_empty()
+            && file_name.to_string_lossy().ends_with(&self.backup)
+    }
+    /*-----------------------------------------------------
+      Rewrite matches in buffer, read from path
+      - decoded files are written back only if UTF-8, with
+        byte order mark restored if the file had one
+    */
+    fn replace_file(
+        &mut self, path: &Path, file_name: &Path, buffer: &str,
+        spans: &[(usize, usize)], enc: Option<&'static Encoding>, utf8_bom: bool
+    ) -> Replaced {
+        if let Some(e) = enc.filter(|&e| e != encoding_rs::UTF_8) {
+            return Replaced::Failed(format!("can't write {} files", e.name()));
+        }
+        let bom = if utf8_bom { "\u{FEFF}" } else { "" };
+        let rep = self.replace.as_deref().unwrap_or("");
+        let re = if self.query.is_none() && !self.custom { self.regx.as_ref() } else { None };
+        let new = replace::replace_spans(buffer, spans, re, rep);
+        if self.dry_run {
+            let name = file_name.to_string_lossy();
+            return Replaced::DryRun(replace::unified_diff(&name, buffer, &new));
+        }
+        let text = format!("{}{}", bom, new);
+        match replace::write_atomic(path, &text, &self.backup) {
+            Ok(()) => {
+                self.num_replaced += 1;
+                Replaced::Written(spans.len())
+            },
+            Err(e) => Replaced::Failed(e.to_string())
+        }
+    }
+    /*-----------------------------------------------------
+      copy of search options, sending results to a Recorder
+      - it searches one file on a pool thread, then is sent
+        back to be merged
+    */
+    fn worker(&self) -> Finder<Recorder> {
+        Finder::<Recorder> {
+            dir: self.dir.clone(),
+            srch_txt: self.srch_txt.clone(),
+            matcher: self.matcher.clone(),
+            custom: self.custom,
+            regx: self.regx.clone(),
+            reg_txt: self.reg_txt.clone(),
+            terms: self.terms.clone(),
+            term_list: self.term_list.clone(),
+            query: self.query.clone(),
+            qry_txt: self.qry_txt.clone(),
+            qry_lines: self.qry_lines,
+            flags: self.flags,
+            ignore_case: self.ignore_case,
+            boundary: self.boundary,
+            fuzzy: self.fuzzy,
+            before: self.before,
+            after: self.after,
+            count_only: self.count_only,
+            invert: self.invert,
+            decompress: self.decompress,
+            extract: self.extract,
+            aggregate: self.aggregate,
+            buf_size: self.buf_size,
+            binary: self.binary,
+            names: self.names,
+            scopes: self.scopes.clone(),
+            item_kinds: self.item_kinds.clone(),
+            encoding: self.encoding,
+            replace: self.replace.clone(),
+            dry_run: self.dry_run,
+            backup: self.backup.clone(),
+            max_size: self.max_size,
+            ..Default::default()
+        }
+    }
+    /*-- queue search of file on pool, results sent by flush --*/
+    fn submit_file(&mut self, file_name: &Path) {
+        let mut w = self.worker();
+        let name = file_name.to_path_buf();
+        let (sender, rcvr) = mpsc::channel();
+        let threads = self.threads;
+        let occurs_done = Arc::clone(&self.occurs_done);
+        let searched_done = Arc::clone(&self.searched_done);
+        let pool = self.pool.get_or_insert_with(|| WorkPool::new(threads));
+        pool.submit(move || {
+            w.search_file(&name);
+            occurs_done.fetch_add(w.num_occurs, Ordering::Relaxed);
+            searched_done.fetch_add(w.num_searched, Ordering::Relaxed);
+            let _ = sender.send(w);
+        });
+        self.pending.push_back(Pending::File(file_name.to_path_buf(), rcvr));
+        self.flush(false);
+    }
+    /*-----------------------------------------------------
+      send finished results to output, in traversal order
+      - stops at first file still being searched, unless
+        too many are pending, or all is set, then waits
+    */
+    fn flush(&mut self, all: bool) {
+        let limit = if all { 0 } else { 4 * self.threads };
+        loop {
+            let wait = self.pending.len() > limit;
+            let rslt = match self.pending.front() {
+                None => break,
+                Some(Pending::Dir(_)) => None,
+                Some(Pending::File(_, rcvr)) if wait => rcvr.recv().ok(),
+                Some(Pending::File(_, rcvr)) => match rcvr.try_recv() {
+                    Ok(w) => Some(w),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => None
+                }
+            };
+            match self.pending.pop_front() {
+                Some(Pending::Dir(_)) if self.past_limit() => {},
+                Some(Pending::Dir(dir)) => self.out.set_dir(&dir),
+                Some(Pending::File(name, _)) => self.merge(&name, rslt),
+                None => break
+            }
+        }
+    }
+    /*-----------------------------------------------------
+      stop search if file or match limit has been reached
+      - only files searched count toward the file limit, not
+        those skipped or failing to open
+      - with threads, matches and files are counted as
+        workers finish, not as results are merged, so
+        traversal stops without waiting on slow files; merge
+        still drops results past the limit, in traversal order
+    */
+    fn check_limits(&mut self) {
+        if self.limit.is_some() {
+            return;
+        }
+        let occurs = self.num_occurs.max(self.occurs_done.load(Ordering::Relaxed));
+        let searched = self.num_searched.max(self.searched_done.load(Ordering::Relaxed));
+        if self.max_matches > 0 && occurs >= self.max_matches {
+            self.limit = Some(Limit::Matches(self.max_matches));
+        }
+        else if self.max_files > 0 && searched >= self.max_files {
+            self.limit = Some(Limit::Files(self.max_files));
+        }
+    }
+    /*-- past file or match limit, so results are dropped, as if never searched --*/
+    fn past_limit(&self) -> bool {
+        (self.max_matches > 0 && self.num_occurs >= self.max_matches)
+            || (self.max_files > 0 && self.num_searched >= self.max_files)
+    }
+    /*-----------------------------------------------------
+      add worker's counts to ours, replay its output
+      - files searched after file or match limit was
+        reached are dropped, so output matches a single
+        thread search
+    */
+    fn merge(&mut self, file_name: &Path, rslt: Option<Finder<Recorder>>) {
+        if self.past_limit() {
+            return;
+        }
+        let w = match rslt {
+            Some(w) => w,
+            None => {  /* search panicked */
+                let e = Error::other("search thread failed");
+                self.report_error(file_name, "search", &e);
+                return;
+            }
+        };
+        self.num_found += w.num_found;
+        self.num_searched += w.num_searched;
+        self.num_occurs += w.num_occurs;
+        self.num_binary += w.num_binary;
+        self.num_errors += w.num_errors;
+        self.num_replaced += w.num_replaced;
+        self.num_large += w.num_large;
+        self.tally.merge(&w.tally);
+        w.out.replay(&mut self.out);
+    }
+    /*-- result for current file, with size and time taken --*/
+    fn file_result(&self, file_name: &Path, status: FileStatus) -> FileResult {
+        let mut fr = FileResult::new(file_name, status, self.pattern());
+        fr.bytes = self.file_bytes;
+        fr.elapsed = self.started.map(|t| t.elapsed()).unwrap_or_default();
+        fr
+    }
+    /*-- send failure to open, decompress, or read file to output --*/
+    fn report_error(&mut self, file_name: &Path, action: &str, err: &std::io::Error) {
+        self.num_errors += 1;
+        let fe = FileError::new(file_name, action, err);
+        self.out.set_error(&fe);
+        self.report_status(file_name, FileStatus::Error, &fe.message);
+    }
+    /*-- send read failure, or decompressed file too large, to output --*/
+    fn report_read_error(&mut self, file_name: &Path, err: &std::io::Error) {
+        match compress::too_large(err) {
+            Some(tl) => {
+                self.num_large += 1;
+                self.report_status(file_name, FileStatus::Skipped, &tl.to_string());
+            },
+            None => self.report_error(file_name, "read", err)
+        }
+    }
+    /*-- send error or skipped status for one file to output --*/
+    fn report_status(&mut self, file_name: &Path, status: FileStatus, msg: &str) {
+        let mut fr = self.file_result(file_name, status);
+        fr.message = msg.to_string();
+        self.out.set_file(&fr);
+    }
+    /*-----------------------------------------------------
+      send results for one file to output
+      - inverted mode counts only files without matches,
+        and sends no match lines
+    */
+    fn report(
+        &mut self, file_name: &Path, found: bool, count: usize,
+        matches: Vec<Match>, blocks: Vec<ContextBlock>
+    ) {
+        self.num_searched += 1;
+        let status = if found { FileStatus::Matched } else { FileStatus::Unmatched };
+        let mut fr = self.file_result(file_name, status);
+        if self.invert {
+            if !found {
+                self.num_found += 1;
+            }
+            self.out.set_file(&fr);
+            return;
+        }
+        if found {
+            self.num_found += 1;
+        }
+        self.num_occurs += count;
+        fr.count = count;
+        fr.message = std::mem::take(&mut self.note);
+        fr.binary = found && self.bin_match;
+        fr.replaced = self.replaced.take();
+        if found && self.terms.is_some() {
+            fr.terms = self.found_terms();
+        }
+        if found && self.extract && !self.count_only {
+            fr.captures = std::mem::take(&mut self.cap_sets);
+        }
+        if found && !self.count_only && !self.extract {
+            fr.items = std::mem::take(&mut self.found_items);
+            if self.before == 0 && self.after == 0 {
+                fr.matches = matches;
+            }
+            else {
+                fr.blocks = blocks;
+            }
+        }
+        self.out.set_file(&fr);
+    }
+    /*-- search text, regex, or query, shown with file results --*/
+    fn pattern(&self) -> &str {
+        if self.query.is_some() { &self.qry_txt }
+        else if let Some(m) = &self.matcher { m.pattern() }
+        else if self.reg_txt.is_empty() { &self.srch_txt } 
+        else { &self.reg_txt }
+    }
+    /*-- count matches of each term, for report_terms --*/
+    fn tally_terms(&mut self, text: &str, spans: &[(usize, usize)]) {
+        if let Some(t) = &self.terms {
+            self.term_counts.resize(t.get_terms().len(), 0);
+            for &(start, end) in spans {
+                if let Some(i) = t.term_index(&text[start..end]) {
+                    self.term_counts[i] += 1;
+                }
+            }
+        }
+    }
+    /*-----------------------------------------------------
+      capture groups of regex matches, added to tally if
+      aggregating, kept for output if extracting
+      - streaming keeps at most MAX_LISTED sets per file
+    */
+    fn collect_captures(&mut self, text: &str, spans: &[(usize, usize)], first_line: usize) {
+        if !(self.extract || self.aggregate) || self.query.is_some() || self.custom || self.invert {
+            return;
+        }
+        let sets = match &self.regx {
+            Some(re) => capture::capture_sets(re, text, spans, first_line),
+            None => return
+        };
+        if self.aggregate {
+            self.tally.add(&sets);
+        }
+        if self.extract {
+            let room = 
+                if self.buf_size > 0 { MAX_LISTED.saturating_sub(self.cap_sets.len()) }
+                else { usize::MAX };
+            if sets.len() > room {
+                self.note = format!("only first {} results listed", MAX_LISTED);
+            }
+            self.cap_sets.extend(sets.into_iter().take(room));
+        }
+    }
+    /*-- terms found in file, with counts, for report --*/
+    fn found_terms(&self) -> Vec<(String, usize)> {
+        match &self.terms {
+            Some(t) => t.get_terms().iter()
+                .zip(self.term_counts.iter())
+                .filter(|(_, &n)| n > 0)
+                .map(|(term, &n)| (term.clone(), n))
+                .collect(),
+            None => Vec::new()
+        }
+    }
+    /*-- send result for binary file searched with Match policy --*/
+    fn report_binary(&mut self, file_name: &Path, found: bool, count: usize) {
+        self.bin_match = true;
+        self.report(file_name, found, count, Vec::new(), Vec::new());
+    }
+    /*-- search names of Rust items in buffer, of selected kinds --*/
+    fn search_items(&mut self, file_name: &Path, buffer: &str) {
+        let kinds = self.item_kinds.clone().unwrap_or_default();
+        self.found_items = items::items(buffer).into_iter()
+            .filter(|it| kinds.is_empty() || kinds.contains(&it.kind))
+            .filter(|it| self.search(&it.name).0)
+            .collect();
+        let count = self.found_items.len();
+        self.report(file_name, count > 0, count, Vec::new(), Vec::new());
+    }
+    /*-----------------------------------------------------
+      Search file name, or path, instead of contents
+      - file is not opened, so binary, encoding, and size
+        limits don't apply
+    */
+    fn search_name(&mut self, file_name: &Path, path: &Path) {
+        self.file_bytes = std::fs::metadata(path).map_or(0, |md| md.len());
+        let text = match self.names {
+            NameMode::Path => path.to_string_lossy().replace('\\', "/"),
+            _ => file_name.to_string_lossy().into_owned()
+        };
+        let (found, spans) = self.search(&text);
+        self.tally_terms(&text, &spans);
+        self.collect_captures(&text, &spans, 1);
+        self.report(file_name, found, spans.len(), Vec::new(), Vec::new());
+    }
+    /*-----------------------------------------------------
+      Search file line by line, reading buf_size chunks,
+      so memory use does not grow with file size
+      - at most MAX_LISTED matches, or context lines, are
+        kept, the rest are only counted
+    */
+    fn stream_file(&mut self, file_name: &Path, mut file: Box<dyn Read>) {
+        /* check first block for binary, then stream it with rest */
+        let mut head = Vec::<u8>::with_capacity(BINARY_BLOCK);
+        let rslt = (&mut file).take(BINARY_BLOCK as u64).read_to_end(&mut head);
+        if let Err(e) = rslt {
+            self.report_read_error(file_name, &e);
+            return;
+        }
+        let mut enc = select_encoding(&head, self.encoding);
+        let binary = enc.is_none() && is_binary(&head);
+        if binary && self.binary == BinaryPolicy::Skip {
+            self.num_binary += 1;
+            self.report_status(file_name, FileStatus::Skipped, "binary file");
+            return;
+        }
+        if self.raw_search(binary) {
+            /* raw lines, memory bounded by longest line */
+            let mut rdr = BufReader::with_capacity(self.buf_size, Cursor::new(head).chain(file));
+            let mut line = Vec::<u8>::new();
+            let mut count = 0usize;
+            loop {
+                line.clear();
+                match rdr.read_until(b'\n', &mut line) {
+                    Ok(0) => break,
+                    Ok(_) => count += self.count_bytes(&line),
+                    Err(e) => {
+                        self.report_read_error(file_name, &e);
+                        return;
+                    }
+                }
+            }
+            self.report_binary(file_name, count > 0, count);
+            return;
+        }
+        let legacy = std::str::from_utf8(&head).is_err_and(|e| e.error_len().is_some());
+        if enc.is_none() && !binary && legacy {
+            enc = Some(encoding::LEGACY);
+        }
+        let rdr = Cursor::new(head).chain(file);
+        let rdr: Box<dyn Read> = match enc {
+            Some(enc) => Box::new(DecodeReader::new(rdr, enc, self.buf_size)),
+            None => Box::new(rdr)
+        };
+        let mut ls = LineStream::new(rdr, self.buf_size);
+        if binary {
+            ls.set_lossy(true);
+        }
+        else {
+            /* invalid UTF-8 past first block is legacy text */
+            ls.set_fallback(Some(encoding::LEGACY));
+        }
+        let use_ctx = 
+            !self.count_only && (self.before > 0 || self.after > 0);
+        let mut cc = ContextCollector::new(self.before, self.after);
+        cc.set_max_lines(MAX_LISTED);
+        let mut matches = Vec::<Match>::new();
+        let mut count = 0usize;
+        loop {
+            let sl = match ls.next_line() {
+                Ok(Some(sl)) => sl,
+                Ok(None) => break,
+                Err(e) => {
+                    self.report_read_error(file_name, &e);
+                    return;
+                }
+            };
+            let spans = self.line_spans(&sl.text);
+            self.tally_terms(&sl.text, &spans);
+            self.collect_captures(&sl.text, &spans, sl.line);
+            count += spans.len();
+            if use_ctx {
+                cc.push(sl.line, &sl.text, !spans.is_empty());
+            }
+            else if !self.count_only {
+                for (start, end) in spans {
+                    if matches.len() == MAX_LISTED {
+                        break;
+                    }
+                    matches.push(Match {
+                        line: sl.line,
+                        end_line: sl.line,
+                        col: sl.text[..start].chars().count() + 1,
+                        offset: sl.offset + start,
+                        text: sl.text.to_string(),
+                        matched: sl.text[start..end].to_string(),
+                        distance: None
+                    });
+                }
+            }
+        }
+        let found = count > 0 || self.is_empty_search();
+        if binary && self.binary == BinaryPolicy::Match {
+            self.report_binary(file_name, found, count);
+            return;
+        }
+        self.set_distances(&mut matches);
+        let truncated = 
+            if use_ctx { cc.is_truncated() } 
+            else { !self.count_only && !self.extract && matches.len() < count };
+        if truncated {
+            self.note = format!("only first {} results listed", MAX_LISTED);
+        }
+        let blocks = cc.into_blocks();
+        self.report(file_name, found, count, matches, blocks);
+    }
+    /*-- edit distances of fuzzy matches from search text --*/
+    fn set_distances(&self, matches: &mut [Match]) {
+        if self.fuzzy.is_some() {
+            for m in matches {
+                m.distance = 
+                    Some(fuzzy::distance(&self.srch_txt, &m.matched, self.ignore_case));
+            }
+        }
+    }
+    /*-- spans of matches in one line, for streaming search --*/
+    fn line_spans(&self, text: &str) -> Vec<(usize, usize)> {
+        match &self.query {
+            Some(q) => query_line_spans(q, text),
+            None => self.find_spans(text)
+        }
+    }
+    /*-- search binary file as bytes: custom matcher, Match policy, no query --*/
+    fn raw_search(&self, binary: bool) -> bool {
+        binary && self.binary == BinaryPolicy::Match && self.custom && self.query.is_none()
+    }
+    /*-- number of custom matcher's matches in raw bytes --*/
+    fn count_bytes(&self, bytes: &[u8]) -> usize {
+        let mut spans = match &self.matcher {
+            Some(m) => m.find_spans_bytes(bytes),
+            None => Vec::new()
+        };
+        if self.boundary != Boundary::None {
+            spans.retain(|&(start, end)| self.boundary.accepts_bytes(bytes, start, end));
+        }
+        spans.len()
+    }
+    /*-- no text, regex, or query, so every file matches --*/
+    fn is_empty_search(&self) -> bool {
+        self.query.is_none() 
+            && !self.custom
+            && self.terms.is_none()
+            && self.reg_txt.is_empty() 
+            && self.srch_txt.is_empty()
+    }
+    /*-- does buffer match, and byte spans of matches to report --*/
+    fn search(&self, buffer: &str) -> (bool, Vec<(usize, usize)>) {
+        if let Some(q) = &self.query {
+            if self.qry_lines {
+                let spans = query_lines(q, buffer);
+                return (!spans.is_empty(), spans);
+            }
+            if q.is_match(buffer) {
+                return (true, q.spans(buffer));
+            }
+            return (false, Vec::new());
+        }
+        let spans = self.find_spans(buffer);
+        /* empty search text matches every file, as before */
+        let found = !spans.is_empty() || self.is_empty_search();
+        (found, spans)
+    }
+    /*-- byte spans (start, end) of all matches in buffer --*/
+    fn find_spans(&self, buffer: &str) -> Vec<(usize, usize)> {
+        let mut spans = match &self.matcher {
+            Some(m) => m.find_spans(buffer),
+            None => Vec::new()
+        };
+        if self.boundary != Boundary::None {
+            spans.retain(|&(start, end)| {
+                self.boundary.accepts(buffer, start, end)
+            });
+        }
+        if self.scoped {
+            scope::retain_in_scopes(&scope::regions(buffer), &self.scopes, &mut spans);
+        }
+        spans
+    }
+}
+
+/*---------------------------------------------------------
+  Evaluate query on each line of buffer
+  - returns spans of positive terms on matching lines, or
+    the whole line if it matches with no positive terms,
+    e.g., NOT foo
+*/
+fn query_lines(q: &Query, buffer: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::<(usize, usize)>::new();
+    let mut offset = 0usize;
+    for raw in buffer.split_inclusive('\n') {
+        let line = raw.trim_end_matches('\n').trim_end_matches('\r');
+        for (start, end) in query_line_spans(q, line) {
+            spans.push((offset + start, offset + end));
+        }
+        offset += raw.len();
+    }
+    spans
+}
+fn query_line_spans(q: &Query, line: &str) -> Vec<(usize, usize)> {
+    if !q.is_match(line) {
+        return Vec::new();
+    }
+    let spans = q.spans(line);
+    if spans.is_empty() {
+        return vec![(0, line.len())];
+    }
+    spans
+}
+
+/*---------------------------------------------------------
+  RegexFlags, parsed from strings like "ims" or "x-u", that
+  use the letters of regex inline flags:
+    i  case-insensitive
+    m  multi-line, ^ and $ match at line ends
+    s  dot matches newline
+    x  verbose, ignore whitespace and allow # comments
+    u  Unicode, on by default, "-u" turns it off
+  Letters after '-' turn flags off.  Text searched is UTF-8,
+  so with Unicode off, patterns that could match part of a
+  char, e.g., . or [^a], are rejected, (?u:.) still works.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegexFlags {
+    pub case_insensitive: bool,
+    pub multi_line: bool,
+    pub dot_all: bool,
+    pub verbose: bool,
+    pub unicode: bool
+}
+impl Default for RegexFlags {
+    fn default() -> Self {
+        RegexFlags {
+            case_insensitive: false,
+            multi_line: false,
+            dot_all: false,
+            verbose: false,
+            unicode: true
+        }
+    }
+}
+impl RegexFlags {
+    pub fn parse(flags: &str) -> std::result::Result<RegexFlags, String> {
+        let mut rf = RegexFlags::default();
+        let mut on = true;
+        for ch in flags.chars() {
+            match ch {
+                '-' => on = false,
+                'i' => rf.case_insensitive = on,
+                'm' => rf.multi_line = on,
+                's' => rf.dot_all = on,
+                'x' => rf.verbose = on,
+                'u' => rf.unicode = on,
+                _ => return Err(format!("unknown regex flag {:?} in {:?}", ch, flags))
+            }
+        }
+        Ok(rf)
+    }
+}
+
+/*-- compile regex, with Unicode case folding if ignore_case --*/
+fn build_regex(pattern: &str, flags: &RegexFlags, ignore_case: bool) 
+    -> std::result::Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case || flags.case_insensitive)
+        .multi_line(flags.multi_line)
+        .dot_matches_new_line(flags.dot_all)
+        .ignore_whitespace(flags.verbose)
+        .unicode(flags.unicode)
+        .build()
+}
+
+/*---------------------------------------------------------
+  Convert byte spans, in increasing order, into Matches
+  with line, column, and text of the containing line
+*/
+pub fn line_matches(buffer: &str, spans: &[(usize, usize)]) -> Vec<Match> {
+    let bytes = buffer.as_bytes();
+    let mut matches = Vec::<Match>::new();
+    let mut line = 1usize;
+    let mut line_start = 0usize;
+    let mut pos = 0usize;
+    for &(start, end) in spans {
+        /* advance to line holding start of this match */
+        for (i, b) in bytes[pos..start].iter().enumerate() {
+            if *b == b'\n' {
+                line += 1;
+                line_start = pos + i + 1;
+            }
+        }
+        pos = start;
+        /* last byte of match, may be in later line */
+        let last = if end > start { end - 1 } else { start };
+        let end_line = 
+            line + bytes[start..last].iter().filter(|&&b| b == b'\n').count();
+        let line_end = bytes[last..].iter().position(|&b| b == b'\n')
+                                    .map_or(buffer.len(), |i| last + i);
+        let text = 
+            if end_line == line {
+                buffer[line_start..line_end].trim_end_matches('\r').to_string()
+            }
+            else {
+                buffer[line_start..line_end].lines().collect::<Vec<&str>>().join("\n")
+            };
+        matches.push(Match {
+            line,
+            end_line,
+            col: buffer[line_start..start].chars().count() + 1,
+            offset: start,
+            text,
+            matched: buffer[start..end].to_string(),
+            distance: None
+        });
+    }
+    matches
+}
+
+/*---------------------------------------------------------
+  Build blocks of lines surrounding matches
+  - each match gets up to before leading and after
+    trailing context lines, all lines of multi-line
+    matches are match lines
+  - overlapping or adjacent windows merge into one block
+*/
+pub fn context_blocks(
+    buffer: &str, matches: &[Match], before: usize, after: usize
+) -> Vec<ContextBlock> {
+    let lines: Vec<&str> = buffer.lines().collect();
+    let mut blocks = Vec::<ContextBlock>::new();
+    let mut last_end = 0usize;  /* last line in current block */
+    for m in matches {
+        let first = m.line.saturating_sub(before).max(1);
+        let end = (m.end_line + after).min(lines.len());
+        let start = 
+            if blocks.is_empty() || first > last_end + 1 {
+                blocks.push(ContextBlock::new());
+                first
+            }
+            else {
+                last_end + 1
+            };
+        let block = blocks.last_mut().unwrap();
+        let is_match = |line: usize| line >= m.line && line <= m.end_line;
+        /* match lines may already be in block as trailing context */
+        for cl in block.iter_mut().filter(|cl| is_match(cl.line)) {
+            cl.is_match = true;
+        }
+        for line in start..=end {
+            block.push(ContextLine {
+                line,
+                text: lines[line - 1].to_string(),
+                is_match: is_match(line)
+            });
+        }
+        last_end = last_end.max(end);
+    }
+    blocks
 }
 
 #[cfg(test)]
@@ -115,39 +1629,78 @@ mod tests {
     struct MockOut {
         dir: PathBuf,
         file: PathBuf,
-        txt: String
+        txt: String,
+        status: FileStatus,
+        message: String,
+        error: Option<ErrorKind>,
+        bytes: u64,
+        items: Vec<Item>,
+        count: usize,
+        matches: Vec<Match>,
+        blocks: Vec<ContextBlock>,
+        binary: bool,
+        replaced: Option<Replaced>,
+        terms: Vec<(String, usize)>,
+        captures: Vec<CaptureSet>
     }
     impl SearchEvent for MockOut {
         fn new() -> Self {
             MockOut {
                 dir: PathBuf::from("."),
                 file: PathBuf::new(),
-                txt: String::new()
+                txt: String::new(),
+                status: FileStatus::Unmatched,
+                message: String::new(),
+                error: None,
+                bytes: 0,
+                items: Vec::new(),
+                count: 0,
+                matches: Vec::new(),
+                blocks: Vec::new(),
+                binary: false,
+                replaced: None,
+                terms: Vec::new(),
+                captures: Vec::new()
             }
         }
         fn set_dir(&mut self, dir: &Path) {
             self.dir = PathBuf::from(dir);
 
         }
-        fn set_file(&mut self, rslt:(&Path, bool, &str)) {
-            self.file = PathBuf::from(rslt.0);
-            self.txt = rslt.2.to_string();
+        fn set_file(&mut self, rslt: &FileResult) {
+            self.file = rslt.path.clone();
+            self.txt = rslt.pattern.clone();
+            self.status = rslt.status;
+            self.message = rslt.message.clone();
+            self.count = rslt.count;
+            self.matches = rslt.matches.clone();
+            self.blocks = rslt.blocks.clone();
+            self.bytes = rslt.bytes;
+            self.items = rslt.items.clone();
+            self.binary = rslt.binary;
+            self.replaced = rslt.replaced.clone();
+            self.terms = rslt.terms.clone();
+            self.captures = rslt.captures.clone();
+        }
+        fn set_error(&mut self, err: &FileError) {
+            self.file = err.path.clone();
+            self.error = Some(err.kind);
         }
     }
     #[test]
     fn test_sets() {
         let mut f = Finder::<MockOut>::new();
-        let dir = Path::new(".\\src");
-        f.do_dir(&Path::new(".\\src"));
+        let dir = Path::new("./src");
+        f.do_dir(Path::new("./src"));
         assert_eq!(f.dir, dir);
         let text = "text";
         f.set_txt("text");
-        assert_eq!(f.txt, text.to_string());
+        assert_eq!(f.srch_txt, text.to_string());
     }
     #[test]
     fn test_trait() {
         let mut f = Finder::<MockOut>::new();
-        let dir = Path::new(".\\src");
+        let dir = Path::new("./src");
         f.do_dir(dir);
         assert_eq!(f.dir, dir);
         let text = "text";
@@ -157,5 +1710,708 @@ mod tests {
         assert_eq!(f.out.dir, dir);
         assert_eq!(f.out.txt, text.to_string());
         assert_eq!(f.out.file, file);
+        assert!(!f.out.matches.is_empty());
+        assert_eq!(f.out.count, f.out.matches.len());
+        assert_eq!(f.get_num_occurs(), f.out.count);
+        assert_eq!(f.out.status, FileStatus::Matched);
+        assert_eq!(f.out.bytes, std::fs::metadata("./src/lib.rs").unwrap().len());
+        assert_eq!(f.out.error, None);
+    }
+    #[test]
+    fn test_threads() {
+        use crate::pool::Event;
+        /* file names and counts, in order sent to output */
+        fn run(threads: usize) -> (Vec<(PathBuf, usize)>, usize, usize) {
+            let mut f = Finder::<Recorder>::new();
+            f.set_threads(threads);
+            f.set_regex(r"fn \w+").unwrap();
+            f.set_aggregate(true);
+            for dir in ["./src", "./examples"] {
+                f.do_dir(Path::new(dir));
+                let mut names: Vec<PathBuf> = std::fs::read_dir(dir).unwrap()
+                    .map(|e| PathBuf::from(e.unwrap().file_name()))
+                    .collect();
+                names.sort();
+                for name in names {
+                    f.do_file(&name);
+                }
+            }
+            f.do_file(Path::new("no_such_file.rs"));
+            f.finish();
+            let files = f.get_app().get_events().iter().filter_map(|ev| match ev {
+                Event::File(fr) => Some((fr.path.clone(), fr.count)),
+                Event::Error(_) => None
+            }).collect();
+            let tally: usize = f.get_captures().get_groups()[0].1.iter().map(|v| v.1).sum();
+            (files, f.get_num_occurs(), tally)
+        }
+        let seq = run(1);
+        let par = run(4);
+        assert!(seq.0.len() > 10 && seq.1 > 0);
+        assert_eq!(seq, par);
+        assert_eq!(par.1, par.2);
+        assert_eq!(par.0.last().unwrap().0, PathBuf::from("no_such_file.rs"));
+    }
+    #[test]
+    fn test_limits() {
+        use crate::pool::Event;
+        fn files(f: &mut Finder<Recorder>) -> Vec<PathBuf> {
+            f.get_app().get_events().iter().filter_map(|ev| match ev {
+                Event::File(fr) if fr.status != FileStatus::Skipped => Some(fr.path.clone()),
+                _ => None
+            }).collect()
+        }
+        /* fixtures, big.rs is over the 1024 byte size limit, edge.rs at it */
+        let dir = std::env::temp_dir().join(format!("text_search_limits_{}", std::process::id()));
+        let _ = std::fs::create_dir(&dir);
+        let sized = |size: usize| {
+            let text = "fn pad() {}\n";
+            format!("{}{}", text, "/".repeat(size - text.len()))
+        };
+        std::fs::write(dir.join("one.rs"), "fn one() {}\nfn two() {}\n").unwrap();
+        std::fs::write(dir.join("two.rs"), "fn three() {}\n").unwrap();
+        std::fs::write(dir.join("three.rs"), "fn four() {}\n").unwrap();
+        std::fs::write(dir.join("big.rs"), sized(1025)).unwrap();
+        std::fs::write(dir.join("edge.rs"), sized(1024)).unwrap();
+        let run = |threads: usize, names: &[&str], setup: &dyn Fn(&mut Finder<Recorder>)| {
+            let mut f = Finder::<Recorder>::new();
+            f.set_threads(threads);
+            f.set_txt("fn");
+            setup(&mut f);
+            f.do_dir(&dir);
+            for name in names {
+                f.do_file(Path::new(name));
+            }
+            f.finish();
+            f
+        };
+        let paths = |names: &[&str]| names.iter().map(PathBuf::from).collect::<Vec<PathBuf>>();
+        for threads in [1, 3] {
+            /* stops after two files searched, skipped files don't count */
+            let names = ["big.rs", "one.rs", "big.rs", "two.rs", "three.rs"];
+            let mut f = run(threads, &names, &|f| { f.set_max_files(2); f.set_max_size(1024); });
+            assert_eq!(f.get_limit(), Some(Limit::Files(2)));
+            assert!(f.is_done());
+            assert_eq!((f.get_num_searched(), f.get_num_large()), (2, 2));
+            assert_eq!(files(&mut f), paths(&["one.rs", "two.rs"]));
+            /* stops after file with first match */
+            let names = ["one.rs", "two.rs", "three.rs"];
+            let mut f = run(threads, &names, &|f| f.set_max_matches(1));
+            assert_eq!(f.get_limit(), Some(Limit::Matches(1)));
+            assert_eq!(files(&mut f), paths(&["one.rs"]));
+            assert_eq!(f.get_num_occurs(), 2);
+            /* traversal stops soon after limit, not after all files */
+            let names = ["two.rs"; 200];
+            let mut f = run(threads, &names, &|f| f.set_max_matches(1));
+            assert!(f.num_files <= 1 + 5 * threads);
+            assert_eq!(files(&mut f).len(), 1);
+            f.set_invert(true);
+            assert!(f.validate().is_err());
+            /* skips files larger than max size, not those at it */
+            let names = ["big.rs", "edge.rs", "one.rs"];
+            let mut f = run(threads, &names, &|f| f.set_max_size(1024));
+            assert_eq!((f.get_num_large(), f.get_num_searched()), (1, 2));
+            assert_eq!(f.get_limit(), None);
+            assert_eq!(files(&mut f), paths(&["edge.rs", "one.rs"]));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn test_names() {
+        let mut f = Finder::<MockOut>::new();
+        f.do_dir(Path::new("./src"));
+        f.set_name_mode(NameMode::Name);
+        f.set_regex(r"^re\w+\.rs$").unwrap();
+        f.do_file(Path::new("replace.rs"));
+        assert_eq!((f.out.status, f.out.count), (FileStatus::Matched, 1));
+        assert_eq!(f.out.bytes, std::fs::metadata("./src/replace.rs").unwrap().len());
+        f.do_file(Path::new("lib.rs"));
+        assert_eq!(f.out.status, FileStatus::Unmatched);
+        /* contents are not searched */
+        f.set_regex("").unwrap();
+        f.set_txt("SearchEvent");
+        f.do_file(Path::new("lib.rs"));
+        assert_eq!(f.out.status, FileStatus::Unmatched);
+        f.set_name_mode(NameMode::Path);
+        f.set_txt("src/lib");
+        f.do_file(Path::new("lib.rs"));
+        assert_eq!(f.out.status, FileStatus::Matched);
+        assert!(f.out.matches.is_empty());
+        assert_eq!(f.get_num_found(), 2);
+        f.set_replace(Some("x"));
+        assert!(f.validate().is_err());
+    }
+    #[test]
+    fn test_scopes() {
+        let dir = std::env::temp_dir().join(format!("text_search_scopes_{}", std::process::id()));
+        let _ = std::fs::create_dir(&dir);
+        let src = concat!(
+            "// set_matcher in line comment\n",
+            "fn main() {\n",
+            "    let s = \"set_matcher in string\";\n",
+            "    set_matcher(s);  /* set_matcher in block comment */\n",
+            "    f.set_matcher(None);\n",
+            "}\n"
+        );
+        std::fs::write(dir.join("scopes.rs"), src).unwrap();
+        let mut f = Finder::<MockOut>::new();
+        f.do_dir(&dir);
+        f.set_txt("set_matcher");
+        let mut counts = Vec::<usize>::new();
+        for name in ["code", "comments", "strings"] {
+            f.set_scopes(&Scope::parse(name).unwrap());
+            f.do_file(Path::new("scopes.rs"));
+            counts.push(f.out.count);
+        }
+        assert_eq!(counts, vec![2, 2, 1]);
+        f.set_scopes(&[]);
+        f.do_file(Path::new("scopes.rs"));
+        assert_eq!(f.out.count, 5);
+        /* scopes combine */
+        f.set_scopes(&[Scope::LineComment, Scope::Str]);
+        f.do_file(Path::new("scopes.rs"));
+        assert_eq!(f.out.count, 2);
+        f.set_buffer_size(64);
+        assert!(f.validate().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn test_items() {
+        let mut f = Finder::<MockOut>::new();
+        f.do_dir(Path::new("./src"));
+        f.set_items(Some(&[ItemKind::Trait]));
+        f.set_txt("SearchEvent");
+        assert!(f.validate().is_ok());
+        f.do_file(Path::new("lib.rs"));
+        assert_eq!(f.out.count, 1);
+        assert_eq!(f.out.items[0].path, "SearchEvent");
+        assert!(f.out.items[0].end_line > f.out.items[0].line);
+        /* impls of trait, by impl header */
+        f.set_items(Some(&[ItemKind::Impl]));
+        f.set_regex(r"^SearchEvent for").unwrap();
+        f.do_file(Path::new("pool.rs"));
+        assert_eq!(f.out.items.len(), 1);
+        assert_eq!(f.out.items[0].name, "SearchEvent for Recorder");
+        f.set_regex("").unwrap();
+        f.set_txt("");
+        f.set_items(Some(&[]));
+        f.do_file(Path::new("pool.rs"));
+        assert!(f.out.items.iter().any(|it| it.path == "Recorder::replay"));
+        f.do_file(Path::new("../Cargo.toml"));
+        assert_eq!(f.out.status, FileStatus::Skipped);
+        f.set_buffer_size(64);
+        assert!(f.validate().is_err());
+    }
+    #[test]
+    fn test_errors() {
+        let mut f = Finder::<MockOut>::new();
+        f.do_dir(Path::new("./src"));
+        f.set_txt("text");
+        f.do_file(Path::new("no_such_file.rs"));
+        assert_eq!(f.out.error, Some(ErrorKind::NotFound));
+        assert_eq!(f.out.file, Path::new("no_such_file.rs"));
+        assert_eq!(f.out.status, FileStatus::Error);
+        assert!(f.out.message.starts_with("can't open file: "));
+        /* directory opens, but can't be read as a file */
+        f.out.error = None;
+        f.do_file(Path::new("../src"));
+        assert!(f.out.error.is_some());
+        f.set_buffer_size(64);
+        f.do_file(Path::new("../src"));
+        assert_eq!(f.get_num_errors(), 3);
+        assert_eq!(f.get_num_found(), 0);
+    }
+    #[test]
+    fn test_count_only() {
+        let mut f = Finder::<MockOut>::new();
+        f.do_dir(Path::new("./src"));
+        f.set_txt("text");
+        f.set_count_only(true);
+        f.do_file(Path::new("lib.rs"));
+        assert!(f.out.count > 1);
+        assert!(f.out.matches.is_empty());
+        assert_eq!(f.get_num_found(), 1);
+    }
+    #[test]
+    fn test_line_matches() {
+        let buffer = "first line\r\nsecond text\nthird text text";
+        let mut f = Finder::<MockOut>::new();
+        f.set_txt("text");
+        let spans = f.find_spans(buffer);
+        assert_eq!(spans.len(), 3);
+        let matches = line_matches(buffer, &spans);
+        assert_eq!(matches[0].line, 2);
+        assert_eq!(matches[0].col, 8);
+        assert_eq!(matches[0].offset, 19);
+        assert_eq!(matches[0].text, "second text");
+        assert_eq!(matches[2].line, 3);
+        assert_eq!(matches[2].col, 12);
+        assert_eq!(matches[2].text, "third text text");
+    }
+    #[test]
+    fn test_ignore_case() {
+        /* Kelvin sign folds to 'k', non-ASCII letters fold too */
+        let buffer = "Ünïcode KEY \u{212A}ey key üNÏCODE";
+        let mut f = Finder::<MockOut>::new();
+        f.set_txt("key");
+        assert_eq!(f.find_spans(buffer).len(), 1);
+        f.set_ignore_case(true);
+        assert_eq!(f.find_spans(buffer).len(), 3);
+        f.set_txt("ünïcode");
+        assert_eq!(f.find_spans(buffer).len(), 2);
+        f.set_regex("K.Y").unwrap();
+        assert_eq!(f.find_spans(buffer).len(), 3);
+        f.set_ignore_case(false);
+        assert_eq!(f.find_spans(buffer).len(), 1);
+    }
+    #[test]
+    fn test_boundary() {
+        let buffer = "dir dirs get_dirs replace_sep_dir (dir)";
+        let mut f = Finder::<MockOut>::new();
+        f.set_txt("dir");
+        assert_eq!(f.find_spans(buffer).len(), 5);
+        f.set_boundary(Boundary::Word);
+        assert_eq!(f.find_spans(buffer), vec![(0, 3), (30, 33), (35, 38)]);
+        f.set_boundary(Boundary::Ident);
+        assert_eq!(f.find_spans(buffer), vec![(0, 3), (35, 38)]);
+        f.set_regex("d.r").unwrap();
+        assert_eq!(f.find_spans(buffer).len(), 2);
+    }
+    #[test]
+    fn test_invalid_regex() {
+        let mut f = Finder::<MockOut>::new();
+        assert!(f.validate().is_ok());
+        let rslt = f.set_regex("fn (\\w+");
+        assert!(rslt.is_err());
+        let msg = f.validate().unwrap_err();
+        assert!(msg.contains("unclosed group"));
+        f.set_regex("fn (\\w+)").unwrap();
+        assert!(f.validate().is_ok());
+    }
+    #[test]
+    fn test_streaming() {
+        let run = |buf_size: usize, before: usize| {
+            let mut f = Finder::<MockOut>::new();
+            f.do_dir(Path::new("./src"));
+            f.set_txt("text");
+            f.set_context(before, before);
+            f.set_buffer_size(buf_size);
+            f.do_file(Path::new("lib.rs"));
+            let found = f.get_num_found();
+            (f.out.count, f.out.matches, f.out.blocks, found)
+        };
+        /* small buffer splits lines and matches across chunks */
+        assert_eq!(run(0, 0), run(7, 0));
+        assert_eq!(run(0, 2), run(7, 2));
+        assert!(run(7, 2).2.len() > 1);
+        /* dense matches are counted, but only MAX_LISTED kept */
+        let dir = std::env::temp_dir().join(format!("text_search_dense_{}", std::process::id()));
+        let _ = std::fs::create_dir(&dir);
+        std::fs::write(dir.join("dense.log"), "x\n".repeat(MAX_LISTED + 5)).unwrap();
+        for before in [0, 1] {
+            let mut f = Finder::<MockOut>::new();
+            f.do_dir(&dir);
+            f.set_txt("x");
+            f.set_context(before, before);
+            f.set_buffer_size(64);
+            f.do_file(Path::new("dense.log"));
+            assert_eq!(f.out.count, MAX_LISTED + 5);
+            let listed = f.out.matches.len() + f.out.blocks.iter().map(|b| b.len()).sum::<usize>();
+            assert!(listed >= MAX_LISTED && listed <= MAX_LISTED + before);
+            assert!(f.out.message.contains("only first"));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn test_binary() {
+        assert!(is_binary(b"abc\x00def"));
+        assert!(is_binary(b"abc\xff\x01def"));
+        assert!(!is_binary(b"abc\xffdef"));  /* legacy text */
+        assert!(!is_binary(b"abc\x01def"));
+        assert!(!is_binary("abc d\u{e9}f".as_bytes()));
+        assert!(!is_binary(&"abc d\u{e9}f".as_bytes()[..6]));
+        let dir = std::env::temp_dir().join(format!("text_search_binary_{}", std::process::id()));
+        let _ = std::fs::create_dir(&dir);
+        std::fs::write(dir.join("bin.dat"), b"abc\x00text\xff\nmore text\n").unwrap();
+        let run = |bp: BinaryPolicy, buf_size: usize| {
+            let mut f = Finder::<MockOut>::new();
+            f.do_dir(&dir);
+            f.set_txt("text");
+            f.set_binary_policy(bp);
+            f.set_buffer_size(buf_size);
+            f.do_file(Path::new("bin.dat"));
+            (f.get_num_binary(), f.get_num_found(), f.out.binary, f.out.matches.len())
+        };
+        for buf_size in [0, 4] {
+            assert_eq!(run(BinaryPolicy::Skip, buf_size), (1, 0, false, 0));
+            assert_eq!(run(BinaryPolicy::Match, buf_size), (0, 1, true, 0));
+            assert_eq!(run(BinaryPolicy::Text, buf_size), (0, 1, false, 2));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn test_encodings() {
+        let dir = std::env::temp_dir().join(format!("text_search_encoding_{}", std::process::id()));
+        let _ = std::fs::create_dir(&dir);
+        let mut utf16 = vec![0xFFu8, 0xFE];
+        for u in "first line\r\nfind caf\u{e9} here\r\n".encode_utf16() {
+            utf16.extend_from_slice(&u.to_le_bytes());
+        }
+        std::fs::write(dir.join("utf16.txt"), utf16).unwrap();
+        std::fs::write(dir.join("cp1252.txt"), b"caf\xe9\ncaf\xe9 again\n").unwrap();
+        let mut late = b"\n".repeat(BINARY_BLOCK);
+        late.extend_from_slice(b"caf\xe9\n");
+        std::fs::write(dir.join("late.txt"), late).unwrap();
+        let run = |file: &str, txt: &str, enc: &str, buf_size: usize| {
+            let mut f = Finder::<MockOut>::new();
+            f.do_dir(&dir);
+            f.set_txt(txt);
+            f.set_encoding(enc).unwrap();
+            f.set_buffer_size(buf_size);
+            f.do_file(Path::new(file));
+            f.out.matches
+        };
+        for buf_size in [0, 5] {
+            let matches = run("utf16.txt", "caf\u{e9}", "", buf_size);
+            assert_eq!(matches.len(), 1);
+            assert_eq!((matches[0].line, matches[0].col), (2, 6));
+            assert_eq!(matches[0].offset, 17);
+            assert_eq!(matches[0].text, "find caf\u{e9} here");
+            /* not valid UTF-8, but no control chars, so legacy text */
+            for enc in ["", "windows-1252"] {
+                let matches = run("cp1252.txt", "caf\u{e9}", enc, buf_size);
+                assert_eq!(matches.len(), 2);
+                assert_eq!((matches[1].line, matches[1].col), (2, 1));
+            }
+            /* legacy text past first block of valid UTF-8 */
+            let matches = run("late.txt", "caf\u{e9}", "", buf_size);
+            assert_eq!(matches.len(), 1);
+            assert_eq!(matches[0].line, BINARY_BLOCK + 1);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+        let mut f = Finder::<MockOut>::new();
+        assert!(f.set_encoding("klingon").is_err());
+    }
+    #[test]
+    fn test_fuzzy() {
+        let mut f = Finder::<MockOut>::new();
+        f.set_txt("Finder");
+        f.set_fuzzy(Some(1));
+        assert!(f.validate().is_ok());
+        f.do_dir(Path::new("./src"));
+        f.do_file(Path::new("lib.rs"));
+        assert!(f.out.count > 0);
+        assert!(f.out.matches.iter().all(|m| m.distance.unwrap() <= 1));
+        assert!(f.out.matches.iter().any(|m| m.matched == "finder" && m.distance == Some(1)));
+        f.set_fuzzy(Some(6));
+        assert!(f.validate().is_err());
+        f.set_fuzzy(Some(1));
+        f.set_regex("abc").unwrap();
+        assert!(f.validate().is_err());
+    }
+    #[test]
+    fn test_terms() {
+        let terms: Vec<String> = 
+            ["unwrap", "no_such_term_here", "expect"].iter().map(|s| s.to_string()).collect();
+        for buf_size in [0, 64] {
+            let mut f = Finder::<MockOut>::new();
+            f.set_terms(&terms).unwrap();
+            f.set_buffer_size(buf_size);
+            assert!(f.validate().is_ok());
+            f.do_dir(Path::new("./src"));
+            f.do_file(Path::new("stream.rs"));
+            assert_eq!(f.out.txt, "3 terms");
+            let found: Vec<&str> = f.out.terms.iter().map(|(t, _)| t.as_str()).collect();
+            assert_eq!(found, vec!["unwrap", "expect"]);
+            assert_eq!(f.out.terms.iter().map(|(_, n)| n).sum::<usize>(), f.out.count);
+            assert!(f.out.matches.iter().all(|m| terms.contains(&m.matched)));
+        }
+        let mut f = Finder::<MockOut>::new();
+        f.set_terms(&terms).unwrap();
+        f.set_regex("abc").unwrap();
+        assert!(f.validate().is_err());
+    }
+    #[test]
+    fn test_invert() {
+        let dir = std::env::temp_dir().join(format!("text_search_invert_{}", std::process::id()));
+        let _ = std::fs::create_dir(&dir);
+        std::fs::write(dir.join("with.txt"), "first line\nneedle here\nneedle again\n").unwrap();
+        std::fs::write(dir.join("without.txt"), "first line\nnothing here\n").unwrap();
+        for buf_size in [0, 8] {
+            let mut f = Finder::<MockOut>::new();
+            f.set_txt("needle");
+            f.set_invert(true);
+            f.set_buffer_size(buf_size);
+            f.do_dir(&dir);
+            f.do_file(Path::new("with.txt"));
+            assert_eq!(f.get_num_found(), 0);
+            f.do_file(Path::new("without.txt"));
+            assert_eq!(f.get_num_found(), 1);
+            assert_eq!(f.get_num_occurs(), 0);
+            assert_eq!(f.out.file, PathBuf::from("without.txt"));
+            assert!(f.out.matches.is_empty());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn test_captures() {
+        for buf_size in [0, 64] {
+            let mut f = Finder::<MockOut>::new();
+            f.set_regex(r#"(?P<crate>\w+) = "(\d+)[^"]*""#).unwrap();
+            f.set_extract(true);
+            f.set_aggregate(true);
+            f.set_buffer_size(buf_size);
+            assert!(f.validate().is_ok());
+            f.do_dir(Path::new("."));
+            f.do_file(Path::new("Cargo.toml"));
+            assert!(f.out.matches.is_empty());
+            let versions: Vec<&str> = f.out.captures.iter()
+                .filter(|cs| cs.groups[0].value == "regex")
+                .map(|cs| cs.groups[1].value.as_str())
+                .collect();
+            assert_eq!(versions, vec!["1"]);
+            let groups = f.get_captures().get_groups();
+            assert_eq!(groups[0].0, "crate");
+            assert!(groups[1].1.iter().any(|(v, _)| v == "0"));
+        }
+        let mut f = Finder::<MockOut>::new();
+        f.set_txt("abc");
+        f.set_aggregate(true);
+        assert!(f.validate().is_err());
+    }
+    #[test]
+    fn test_compressed() {
+        let dir = std::env::temp_dir().join(format!("text_search_compress_{}", std::process::id()));
+        let _ = std::fs::create_dir(&dir);
+        let text = "first line\nneedle in line two\n";
+        let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        enc.write_all(text.as_bytes()).unwrap();
+        std::fs::write(dir.join("app.log.gz"), enc.finish().unwrap()).unwrap();
+        let zst = ruzstd::encoding::compress_to_vec(
+            text.as_bytes(), ruzstd::encoding::CompressionLevel::Fastest
+        );
+        std::fs::write(dir.join("dump.zst"), zst).unwrap();
+        for buf_size in [0, 8] {
+            for file in ["app.log.gz", "dump.zst"] {
+                let mut f = Finder::<MockOut>::new();
+                f.do_dir(&dir);
+                f.set_txt("needle");
+                f.set_buffer_size(buf_size);
+                f.do_file(Path::new(file));
+                assert_eq!(f.get_num_found(), 0);  /* binary, skipped */
+                f.set_decompress(true);
+                f.do_file(Path::new(file));
+                assert_eq!(f.get_num_found(), 1);
+                assert_eq!(f.out.file, PathBuf::from(file));
+                assert_eq!((f.out.matches[0].line, f.out.matches[0].col), (2, 1));
+            }
+        }
+        /* decompressed size past max size is skipped, not searched */
+        let opts = lzma_rust2::XzOptions::with_preset(6);
+        let mut enc = lzma_rust2::XzWriter::new(Vec::new(), opts).unwrap();
+        enc.write_all(text.repeat(10_000).as_bytes()).unwrap();
+        std::fs::write(dir.join("big.log.xz"), enc.finish().unwrap()).unwrap();
+        for buf_size in [0, 8] {
+            let mut f = Finder::<MockOut>::new();
+            f.do_dir(&dir);
+            f.set_txt("needle");
+            f.set_buffer_size(buf_size);
+            f.set_decompress(true);
+            f.do_file(Path::new("big.log.xz"));
+            assert_eq!(f.out.count, 10_000);
+            f.set_max_size(10_000);
+            f.do_file(Path::new("big.log.xz"));
+            assert_eq!((f.get_num_large(), f.get_num_errors()), (1, 0));
+            assert_eq!(f.out.status, FileStatus::Skipped);
+            assert_eq!(f.out.message, "decompressed size larger than 10000 bytes");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    /*-- user-supplied matcher, finds runs of digits --*/
+    #[derive(Debug)]
+    struct Digits;
+    impl Matcher for Digits {
+        fn find_spans(&self, text: &str) -> Vec<(usize, usize)> {
+            let mut spans = Vec::new();
+            let mut start = None;
+            for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+                match (c.is_ascii_digit(), start) {
+                    (true, None) => start = Some(i),
+                    (false, Some(s)) => { spans.push((s, i)); start = None; },
+                    _ => {}
+                }
+            }
+            spans
+        }
+        fn pattern(&self) -> &str {
+            "digits"
+        }
+    }
+    #[test]
+    fn test_matcher() {
+        let mut f = Finder::<MockOut>::new();
+        f.set_txt("not used");
+        f.set_matcher(Some(Box::new(Digits)));
+        assert_eq!(f.find_spans("ver 1.25, 3 x"), vec![(4, 5), (6, 8), (10, 11)]);
+        f.set_boundary(Boundary::Word);
+        assert_eq!(f.find_spans("v1 and 22"), vec![(7, 9)]);
+        f.do_dir(Path::new("./src"));
+        f.do_file(Path::new("stream.rs"));
+        assert_eq!(f.out.txt, "digits");
+        assert!(f.out.count > 0);
+        /* built-in matcher again */
+        f.set_matcher(None);
+        assert_eq!(f.find_spans("not used, not Used"), vec![(0, 8)]);
+        f.set_ignore_case(true);
+        assert_eq!(f.find_spans("not used, not Used"), vec![(0, 8), (10, 18)]);
+    }
+    /* finds 0xDE 0xAD, which is never valid UTF-8, so only in raw bytes */
+    #[derive(Debug)]
+    struct Dead;
+    impl Matcher for Dead {
+        fn find_spans(&self, _text: &str) -> Vec<(usize, usize)> {
+            Vec::new()
+        }
+        fn find_spans_bytes(&self, bytes: &[u8]) -> Vec<(usize, usize)> {
+            bytes.windows(2).enumerate()
+                .filter(|(_, w)| w == b"\xde\xad")
+                .map(|(i, _)| (i, i + 2))
+                .collect()
+        }
+        fn pattern(&self) -> &str {
+            "dead"
+        }
+    }
+    #[test]
+    fn test_matcher_bytes() {
+        let dir = std::env::temp_dir().join(format!("text_search_bytes_{}", std::process::id()));
+        let _ = std::fs::create_dir(&dir);
+        std::fs::write(dir.join("raw.bin"), b"ab\x00\xde\xad\n\xbe\xef\xde\xad\n").unwrap();
+        for buf_size in [0, 4] {
+            let mut f = Finder::<MockOut>::new();
+            f.do_dir(&dir);
+            f.set_matcher(Some(Box::new(Dead)));
+            f.set_binary_policy(BinaryPolicy::Match);
+            f.set_buffer_size(buf_size);
+            f.do_file(Path::new("raw.bin"));
+            assert_eq!((f.get_num_found(), f.out.binary, f.out.count), (1, true, 2));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn test_replace() {
+        let dir = std::env::temp_dir().join(format!("text_search_replace_{}", std::process::id()));
+        let _ = std::fs::create_dir(&dir);
+        let path = dir.join("src.rs");
+        std::fs::write(&path, "let a = foo(1);\nlet b = 2;\n").unwrap();
+        let mut f = Finder::<MockOut>::new();
+        f.do_dir(&dir);
+        f.set_regex(r"foo\((\d+)\)").unwrap();
+        f.set_replace(Some("bar($1)"));
+        f.set_dry_run(true);
+        f.do_file(Path::new("src.rs"));
+        match f.out.replaced.take() {
+            Some(Replaced::DryRun(diff)) => {
+                assert!(diff.contains("\n-let a = foo(1);\n+let a = bar(1);\n"))
+            },
+            other => panic!("expected diff, got {:?}", other)
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "let a = foo(1);\nlet b = 2;\n");
+        f.set_dry_run(false);
+        f.set_backup(".bak");
+        f.do_file(Path::new("src.rs"));
+        assert_eq!(f.out.replaced.take(), Some(Replaced::Written(1)));
+        assert_eq!(f.get_num_replaced(), 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "let a = bar(1);\nlet b = 2;\n");
+        /* backups are not searched again */
+        f.do_file(Path::new("src.rs.bak"));
+        assert!(f.out.replaced.is_none());
+        assert_eq!(std::fs::read_to_string(dir.join("src.rs.bak")).unwrap(), "let a = foo(1);\nlet b = 2;\n");
+        assert!(f.validate().is_ok());
+        /* forced UTF-8 adds no byte order mark, one found is kept */
+        f.set_backup("");
+        f.set_encoding("utf-8").unwrap();
+        for bom in ["", "\u{FEFF}"] {
+            std::fs::write(&path, format!("{}let a = foo(2);\n", bom)).unwrap();
+            f.do_file(Path::new("src.rs"));
+            assert_eq!(f.out.replaced.take(), Some(Replaced::Written(1)));
+            let text = format!("{}let a = bar(2);\n", bom);
+            assert_eq!(std::fs::read(&path).unwrap(), text.as_bytes());
+        }
+        f.set_buffer_size(1024);
+        assert!(f.validate().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn test_query() {
+        let buffer = "unsafe { a() }\n// SAFETY: ok\nunsafe { b() }\n";
+        let mut f = Finder::<MockOut>::new();
+        f.set_query(r#"unsafe AND NOT "// SAFETY""#).unwrap();
+        assert!(!f.search(buffer).0);
+        f.set_query_lines(true);
+        let (found, spans) = f.search(buffer);
+        assert!(found);
+        assert_eq!(spans, vec![(0, 6), (29, 35)]);
+        f.set_query("NOT unsafe").unwrap();
+        assert_eq!(f.search(buffer).1, vec![(15, 28)]);
+        /* streaming can't evaluate a query on whole files */
+        f.set_buffer_size(64);
+        assert!(f.validate().is_ok());
+        f.set_query_lines(false);
+        assert!(f.validate().is_err());
+        assert!(f.set_query("unsafe AND (").is_err());
+    }
+    #[test]
+    fn test_regex_flags() {
+        assert_eq!(RegexFlags::parse("").unwrap(), RegexFlags::default());
+        let rf = RegexFlags::parse("ms-u").unwrap();
+        assert!(rf.multi_line && rf.dot_all && !rf.unicode && !rf.verbose);
+        assert!(RegexFlags::parse("q").is_err());
+        /* with Unicode off, . could match part of a char */
+        let mut f = Finder::<MockOut>::new();
+        f.set_regex_flags(RegexFlags::parse("-u").unwrap());
+        assert!(f.set_regex("a.c").is_err());
+        assert!(f.validate().unwrap_err().contains("(?u:.)"));
+        f.set_regex(r"a(?u:.)c\w").unwrap();
+        assert_eq!(f.find_spans("a\u{e9}c_ a\u{e9}c\u{e9}").len(), 1);
+        /* query regex terms use flags too */
+        f.set_regex_flags(RegexFlags::parse("i").unwrap());
+        f.set_query("re:FN").unwrap();
+        assert!(f.search("fn main").0);
+        let buffer = "fn main() {\r\n    body\r\n}\nfn other() {}\n";
+        let mut f = Finder::<MockOut>::new();
+        f.set_regex(r"^fn \w+\(\) \{.*?\}").unwrap();
+        assert!(f.find_spans(buffer).is_empty());
+        f.set_regex_flags(RegexFlags::parse("ms").unwrap());
+        let spans = f.find_spans(buffer);
+        assert_eq!(spans.len(), 2);
+        let matches = line_matches(buffer, &spans);
+        assert_eq!((matches[0].line, matches[0].end_line), (1, 3));
+        assert_eq!(matches[0].text, "fn main() {\n    body\n}");
+        assert_eq!((matches[1].line, matches[1].end_line), (4, 4));
+        let blocks = context_blocks(buffer, &matches, 0, 0);
+        assert_eq!(blocks.len(), 1);
+        assert!(blocks[0].iter().all(|cl| cl.is_match));
+        f.set_regex_flags(RegexFlags::parse("x").unwrap());
+        f.set_regex(r"fn other").unwrap();
+        assert!(f.find_spans(buffer).is_empty());
+        f.set_regex(r"fn \s other  # spaces ignored").unwrap();
+        assert_eq!(f.find_spans(buffer).len(), 1);
+    }
+    #[test]
+    fn test_context_blocks() {
+        let buffer = "l1\nfoo\nl3\nl4\nfoo\nl6\nl7\nl8\nl9\nfoo";
+        let mut f = Finder::<MockOut>::new();
+        f.set_txt("foo");
+        let matches = line_matches(buffer, &f.find_spans(buffer));
+        let blocks = context_blocks(buffer, &matches, 1, 1);
+        /* windows around lines 2 and 5 are adjacent, so merge */
+        assert_eq!(blocks.len(), 2);
+        let lines: Vec<usize> = blocks[0].iter().map(|cl| cl.line).collect();
+        assert_eq!(lines, vec![1, 2, 3, 4, 5, 6]);
+        assert!(blocks[0][4].is_match);
+        assert!(!blocks[0][3].is_match);
+        let lines: Vec<usize> = blocks[1].iter().map(|cl| cl.line).collect();
+        assert_eq!(lines, vec![9, 10]);
     }
 }
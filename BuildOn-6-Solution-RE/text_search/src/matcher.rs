@@ -0,0 +1,173 @@
+/////////////////////////////////////////////////////////////
+// TextFinder::text_search::matcher.rs                     //
+//   - strategies Finder uses to find matches              //
+/////////////////////////////////////////////////////////////
+/*
+  Matcher finds matches in text and returns their byte
+  spans.  Finder builds one of the matchers here from its
+  search text, regex, terms, and fuzzy settings, or uses a
+  matcher supplied by its client with Finder::set_matcher.
+  - find_spans gets UTF-8 text, files in other encodings
+    are decoded before matching
+  - find_spans_bytes gets raw bytes, Finder uses it for
+    binary files searched by a custom matcher, so it sees
+    bytes that aren't valid UTF-8; the default searches
+    each valid UTF-8 run with find_spans, so matchers for
+    text need only implement find_spans
+  - Finder filters spans by word boundary, if set, so
+    matchers don't need to
+  - matchers are shared by Finder's pool threads, so must
+    be Send and Sync
+*/
+
+use crate::{build_regex, fuzzy, RegexFlags, Terms};
+use regex::Regex;
+use std::fmt::Debug;
+
+pub trait Matcher: Debug + Send + Sync {
+    /*-- byte spans (start, end) of matches, in order, not overlapping --*/
+    fn find_spans(&self, text: &str) -> Vec<(usize, usize)>;
+    /*-- byte spans of matches in bytes, not all of which need be UTF-8 --*/
+    fn find_spans_bytes(&self, bytes: &[u8]) -> Vec<(usize, usize)> {
+        let mut spans = Vec::<(usize, usize)>::new();
+        let mut base = 0usize;
+        for chunk in bytes.utf8_chunks() {
+            let valid = chunk.valid();
+            spans.extend(self.find_spans(valid).into_iter().map(|(s, e)| (base + s, base + e)));
+            base += valid.len() + chunk.invalid().len();
+        }
+        spans
+    }
+    /*-- text shown with each file's results --*/
+    fn pattern(&self) -> &str;
+}
+
+/*---------------------------------------------------------
+  Finds literal text
+  - case-insensitive search uses an escaped regex, so
+    matching applies Unicode case folding without making
+    lowercased copies of each file
+*/
+#[derive(Debug, Clone)]
+pub struct LiteralMatcher {
+    txt: String,
+    regx: Option<Regex>
+}
+impl LiteralMatcher {
+    pub fn new(txt: &str, ignore_case: bool) -> Self {
+        let regx =
+            if ignore_case {
+                Some(build_regex(&regex::escape(txt), &RegexFlags::default(), true)
+                        .expect("escaped text is valid regex"))
+            }
+            else {
+                None
+            };
+        LiteralMatcher { txt: txt.to_string(), regx }
+    }
+}
+impl Matcher for LiteralMatcher {
+    fn find_spans(&self, text: &str) -> Vec<(usize, usize)> {
+        if self.txt.is_empty() {
+            return Vec::new();
+        }
+        match &self.regx {
+            Some(re) => re.find_iter(text).map(|m| (m.start(), m.end())).collect(),
+            None => text.match_indices(self.txt.as_str())
+                        .map(|(start, s)| (start, start + s.len()))
+                        .collect()
+        }
+    }
+    fn pattern(&self) -> &str {
+        &self.txt
+    }
+}
+
+/*-- Finds matches of a compiled regex --*/
+#[derive(Debug, Clone)]
+pub struct RegexMatcher {
+    regx: Regex
+}
+impl RegexMatcher {
+    pub fn new(regx: Regex) -> Self {
+        RegexMatcher { regx }
+    }
+}
+impl Matcher for RegexMatcher {
+    fn find_spans(&self, text: &str) -> Vec<(usize, usize)> {
+        self.regx.find_iter(text).map(|m| (m.start(), m.end())).collect()
+    }
+    fn pattern(&self) -> &str {
+        self.regx.as_str()
+    }
+}
+
+/*-- Finds text within k edits, see fuzzy.rs --*/
+#[derive(Debug, Clone)]
+pub struct FuzzyMatcher {
+    txt: String,
+    fuzzy: fuzzy::Fuzzy
+}
+impl FuzzyMatcher {
+    pub fn new(txt: &str, k: usize, ignore_case: bool) -> Self {
+        FuzzyMatcher { txt: txt.to_string(), fuzzy: fuzzy::Fuzzy::new(txt, k, ignore_case) }
+    }
+}
+impl Matcher for FuzzyMatcher {
+    fn find_spans(&self, text: &str) -> Vec<(usize, usize)> {
+        self.fuzzy.find(text)
+    }
+    fn pattern(&self) -> &str {
+        &self.txt
+    }
+}
+
+/*-- Finds many literal terms in one pass, see terms.rs --*/
+#[derive(Debug, Clone)]
+pub struct TermsMatcher {
+    terms: Terms,
+    txt: String
+}
+impl TermsMatcher {
+    pub fn new(terms: Terms) -> Self {
+        let txt = format!("{} terms", terms.get_terms().len());
+        TermsMatcher { terms, txt }
+    }
+}
+impl Matcher for TermsMatcher {
+    fn find_spans(&self, text: &str) -> Vec<(usize, usize)> {
+        self.terms.spans(text)
+    }
+    fn pattern(&self) -> &str {
+        &self.txt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn matchers() {
+        let text = "Finder finds; FINDER found";
+        let lit = LiteralMatcher::new("Finder", false);
+        assert_eq!(lit.find_spans(text), vec![(0, 6)]);
+        let lit = LiteralMatcher::new("finder", true);
+        assert_eq!(lit.find_spans(text), vec![(0, 6), (14, 20)]);
+        assert!(LiteralMatcher::new("", false).find_spans(text).is_empty());
+        let re = RegexMatcher::new(Regex::new(r"f\w+").unwrap());
+        assert_eq!(re.find_spans(text).len(), 2);
+        assert_eq!(re.pattern(), r"f\w+");
+        let fz = FuzzyMatcher::new("finds", 1, false);
+        assert_eq!(fz.find_spans(text), vec![(7, 12)]);
+        let terms: Vec<String> = vec!["finds".to_string(), "found".to_string()];
+        let tm = TermsMatcher::new(Terms::new(&terms, false).unwrap());
+        assert_eq!(tm.find_spans(text), vec![(7, 12), (21, 26)]);
+        assert_eq!(tm.pattern(), "2 terms");
+        /* bytes, invalid UTF-8 is skipped by default */
+        let lit = LiteralMatcher::new("Finder", false);
+        assert_eq!(lit.find_spans_bytes(b"\xffFinder\x00Finder"), vec![(1, 7), (8, 14)]);
+        /* trait objects, as Finder holds them */
+        let all: Vec<Box<dyn Matcher>> = vec![Box::new(lit), Box::new(tm)];
+        assert!(all.iter().all(|m| !m.find_spans(text).is_empty()));
+    }
+}
@@ -0,0 +1,149 @@
+/////////////////////////////////////////////////////////////
+// TextFinder::text_search::pool.rs                        //
+//   - search files on a bounded pool of threads           //
+/////////////////////////////////////////////////////////////
+/*
+  WorkPool runs jobs on a fixed number of threads.
+  - jobs wait in a bounded queue, so submit blocks when
+    the threads fall behind, instead of queuing a whole
+    dir tree
+  - a job that panics is dropped, its thread keeps running
+
+  Recorder is the SearchEvent used by Finder's worker
+  copies.  It saves the events sent for one file, so Finder
+  can replay them to its own output in traversal order.
+*/
+
+use crate::{FileError, FileResult, SearchEvent};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+#[derive(Debug)]
+pub struct WorkPool {
+    sender: Option<SyncSender<Job>>,
+    threads: Vec<JoinHandle<()>>
+}
+impl WorkPool {
+    /*-- start num_threads threads, at least one --*/
+    pub fn new(num_threads: usize) -> Self {
+        let n = num_threads.max(1);
+        let (sender, rcvr) = mpsc::sync_channel::<Job>(2 * n);
+        let rcvr = Arc::new(Mutex::new(rcvr));
+        let threads = (0..n).map(|_| {
+            let rcvr = Arc::clone(&rcvr);
+            thread::spawn(move || loop {
+                let job = match rcvr.lock() {
+                    Ok(r) => r.recv(),
+                    Err(_) => break
+                };
+                match job {
+                    Ok(job) => {
+                        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                    },
+                    Err(_) => break  /* pool dropped */
+                }
+            })
+        }).collect();
+        WorkPool { sender: Some(sender), threads }
+    }
+    /*-- queue job, blocks while queue is full --*/
+    pub fn submit<F: FnOnce() + Send + 'static>(&self, job: F) {
+        if let Some(s) = &self.sender {
+            let _ = s.send(Box::new(job));
+        }
+    }
+    pub fn num_threads(&self) -> usize {
+        self.threads.len()
+    }
+}
+/*-- threads finish queued jobs, then exit --*/
+impl Drop for WorkPool {
+    fn drop(&mut self) {
+        self.sender.take();
+        for h in self.threads.drain(..) {
+            let _ = h.join();
+        }
+    }
+}
+
+/*-- one SearchEvent call, saved for replay --*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    File(Box<FileResult>),
+    Error(FileError)
+}
+
+#[derive(Debug, Default)]
+pub struct Recorder {
+    events: Vec<Event>
+}
+impl Recorder {
+    pub fn get_events(&self) -> &[Event] {
+        &self.events
+    }
+    /*-- send saved events to out, in the order they were made --*/
+    pub fn replay<T: SearchEvent>(&self, out: &mut T) {
+        for ev in &self.events {
+            match ev {
+                Event::File(fr) => out.set_file(fr),
+                Event::Error(err) => out.set_error(err)
+            }
+        }
+    }
+}
+impl SearchEvent for Recorder {
+    fn new() -> Self {
+        Self::default()
+    }
+    /*-- Finder sends dirs to its own output, in order --*/
+    fn set_dir(&mut self, _dir: &Path) {}
+    fn set_file(&mut self, rslt: &FileResult) {
+        self.events.push(Event::File(Box::new(rslt.clone())));
+    }
+    fn set_error(&mut self, err: &FileError) {
+        self.events.push(Event::Error(err.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn pool() {
+        let pool = WorkPool::new(3);
+        assert_eq!(pool.num_threads(), 3);
+        let (tx, rx) = mpsc::channel();
+        for i in 0..20usize {
+            let tx = tx.clone();
+            pool.submit(move || {
+                if i == 5 {
+                    panic!("job panics, thread survives");
+                }
+                tx.send(i).unwrap();
+            });
+        }
+        drop(pool);  /* waits for jobs */
+        drop(tx);
+        let mut done: Vec<usize> = rx.iter().collect();
+        done.sort();
+        assert_eq!(done.len(), 19);
+        assert!(!done.contains(&5));
+    }
+    #[test]
+    fn recorder() {
+        let mut rec = Recorder::new();
+        rec.set_dir(Path::new("src"));
+        rec.set_file(&FileResult::new(Path::new("lib.rs"), crate::FileStatus::Matched, "x"));
+        let err = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        rec.set_error(&FileError::new(Path::new("x.rs"), "open", &err));
+        assert_eq!(rec.get_events().len(), 2);
+        let mut copy = Recorder::new();
+        rec.replay(&mut copy);
+        assert_eq!(copy.get_events(), rec.get_events());
+    }
+}
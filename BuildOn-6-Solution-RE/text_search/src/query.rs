@@ -0,0 +1,396 @@
+/////////////////////////////////////////////////////////////
+// TextFinder::text_search::query.rs                       //
+//   - boolean queries combining text and regex terms      //
+/////////////////////////////////////////////////////////////
+/*
+  Query syntax:
+    unsafe AND NOT "// SAFETY"
+    (fn OR struct) AND re:"impl\s+\w+"
+  - terms are words, "quoted phrases", or regexes written
+    re:pattern or re:"pattern"
+  - operators are AND, OR, NOT, and parentheses, with
+    precedence NOT > AND > OR
+  - adjacent terms without an operator are ANDed
+  - inside quotes, \" and \\ escape quote and backslash
+  - regex terms are built with the same flags as /R, see
+    RegexFlags in lib.rs
+*/
+
+use crate::{build_regex, Boundary, RegexFlags};
+use regex::Regex;
+use std::fmt;
+
+/*-- error in query text, pos is char index into query --*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub pos: usize,
+    pub msg: String
+}
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.msg, self.pos)
+    }
+}
+impl std::error::Error for QueryError {}
+
+/*-- text or regex term of a query --*/
+#[derive(Debug, Clone)]
+pub struct Term {
+    pub text: String,
+    pub is_regex: bool,
+    re: Regex
+}
+
+/*-- parsed query, leaves index into Query::terms --*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Term(usize),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Word(String),
+    Phrase(String),
+    Regex(String)
+}
+
+/*---------------------------------------------------------
+  Query holds parsed expression and compiled terms
+  - terms under an even number of NOTs are positive, their
+    matches are reported as match spans
+*/
+#[derive(Debug, Clone)]
+pub struct Query {
+    expr: Expr,
+    terms: Vec<Term>,
+    positive: Vec<usize>,
+    boundary: Boundary
+}
+impl Query {
+    /*-- parse query, compiling terms with ignore_case --*/
+    pub fn new(src: &str, ignore_case: bool) -> Result<Query, QueryError> {
+        Query::with_flags(src, ignore_case, &RegexFlags::default())
+    }
+    /*-- parse query, compiling regex terms with flags too --*/
+    pub fn with_flags(src: &str, ignore_case: bool, flags: &RegexFlags) -> Result<Query, QueryError> {
+        let tokens = tokenize(src)?;
+        let mut parser = Parser {
+            tokens, pos: 0, end: src.chars().count(),
+            terms: Vec::new(), ignore_case, flags: *flags
+        };
+        let expr = parser.parse_or()?;
+        if let Some((tok, pos)) = parser.tokens.get(parser.pos) {
+            let msg = format!("unexpected {:?}", tok);
+            return Err(QueryError { pos: *pos, msg });
+        }
+        let mut positive = Vec::<usize>::new();
+        collect_positive(&expr, true, &mut positive);
+        Ok(Query { expr, terms: parser.terms, positive, boundary: Boundary::None })
+    }
+    /*-- accept only term matches on boundaries --*/
+    pub fn set_boundary(&mut self, b: Boundary) {
+        self.boundary = b;
+    }
+    pub fn get_terms(&self) -> &[Term] {
+        &self.terms
+    }
+    pub fn get_expr(&self) -> &Expr {
+        &self.expr
+    }
+    /*-- does text satisfy query? --*/
+    pub fn is_match(&self, text: &str) -> bool {
+        self.eval(&self.expr, text)
+    }
+    /*-- sorted, non-overlapping spans of positive terms in text --*/
+    pub fn spans(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut spans = Vec::<(usize, usize)>::new();
+        for &i in &self.positive {
+            spans.extend(self.term_spans(i, text));
+        }
+        spans.sort_unstable();
+        let mut rtn = Vec::<(usize, usize)>::new();
+        for span in spans {
+            match rtn.last() {
+                Some(&(_, end)) if span.0 < end => {},
+                _ => rtn.push(span)
+            }
+        }
+        rtn
+    }
+    fn term_spans(&self, i: usize, text: &str) -> Vec<(usize, usize)> {
+        self.terms[i].re.find_iter(text)
+            .map(|m| (m.start(), m.end()))
+            .filter(|&(start, end)| self.boundary.accepts(text, start, end))
+            .collect()
+    }
+    fn eval(&self, expr: &Expr, text: &str) -> bool {
+        match expr {
+            Expr::Term(i) => {
+                if self.boundary == Boundary::None {
+                    self.terms[*i].re.is_match(text)
+                }
+                else {
+                    !self.term_spans(*i, text).is_empty()
+                }
+            },
+            Expr::Not(e) => !self.eval(e, text),
+            Expr::And(l, r) => self.eval(l, text) && self.eval(r, text),
+            Expr::Or(l, r) => self.eval(l, text) || self.eval(r, text)
+        }
+    }
+}
+
+fn collect_positive(expr: &Expr, positive: bool, out: &mut Vec<usize>) {
+    match expr {
+        Expr::Term(i) => if positive { out.push(*i); },
+        Expr::Not(e) => collect_positive(e, !positive, out),
+        Expr::And(l, r) | Expr::Or(l, r) => {
+            collect_positive(l, positive, out);
+            collect_positive(r, positive, out);
+        }
+    }
+}
+
+/*-- split query into tokens, each with its char position --*/
+fn tokenize(src: &str) -> Result<Vec<(Token, usize)>, QueryError> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::<(Token, usize)>::new();
+    let mut i = 0usize;
+    while i < chars.len() {
+        let ch = chars[i];
+        let start = i;
+        if ch.is_whitespace() {
+            i += 1;
+        }
+        else if ch == '(' {
+            tokens.push((Token::LParen, start));
+            i += 1;
+        }
+        else if ch == ')' {
+            tokens.push((Token::RParen, start));
+            i += 1;
+        }
+        else if ch == '"' {
+            let (phrase, next) = read_quoted(&chars, i)?;
+            tokens.push((Token::Phrase(phrase), start));
+            i = next;
+        }
+        else if chars[i..].starts_with(&['r', 'e', ':']) {
+            i += 3;
+            if i < chars.len() && chars[i] == '"' {
+                let (pattern, next) = read_quoted(&chars, i)?;
+                tokens.push((Token::Regex(pattern), start));
+                i = next;
+            }
+            else {
+                /* unquoted regex ends at space or unbalanced ')' */
+                let mut depth = 0i32;
+                let mut pattern = String::new();
+                while i < chars.len() && !chars[i].is_whitespace() {
+                    match chars[i] {
+                        '(' => depth += 1,
+                        ')' if depth == 0 => break,
+                        ')' => depth -= 1,
+                        _ => {}
+                    }
+                    pattern.push(chars[i]);
+                    i += 1;
+                }
+                if pattern.is_empty() {
+                    let msg = "empty regex term".to_string();
+                    return Err(QueryError { pos: start, msg });
+                }
+                tokens.push((Token::Regex(pattern), start));
+            }
+        }
+        else {
+            let mut word = String::new();
+            while i < chars.len()
+                  && !chars[i].is_whitespace()
+                  && !"()\"".contains(chars[i]) {
+                word.push(chars[i]);
+                i += 1;
+            }
+            let tok = match word.as_str() {
+                "AND" => Token::And,
+                "OR" => Token::Or,
+                "NOT" => Token::Not,
+                _ => Token::Word(word)
+            };
+            tokens.push((tok, start));
+        }
+    }
+    Ok(tokens)
+}
+
+/*-- read "..." starting at chars[i], returns text and next index --*/
+fn read_quoted(chars: &[char], i: usize) -> Result<(String, usize), QueryError> {
+    let mut text = String::new();
+    let mut j = i + 1;
+    while j < chars.len() {
+        match chars[j] {
+            '"' => return Ok((text, j + 1)),
+            '\\' if j + 1 < chars.len()
+                    && (chars[j + 1] == '"' || chars[j + 1] == '\\') => {
+                text.push(chars[j + 1]);
+                j += 2;
+            },
+            c => {
+                text.push(c);
+                j += 1;
+            }
+        }
+    }
+    Err(QueryError { pos: i, msg: "unterminated quote".to_string() })
+}
+
+/*---------------------------------------------------------
+  Recursive descent parser:
+    or    := and ("OR" and)*
+    and   := unary ("AND"? unary)*
+    unary := "NOT" unary | "(" or ")" | term
+*/
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+    terms: Vec<Term>,
+    ignore_case: bool,
+    flags: RegexFlags
+}
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(tok, _)| tok)
+    }
+    /*-- position of current token, or end of query --*/
+    fn here(&self) -> usize {
+        match self.tokens.get(self.pos) {
+            Some((_, pos)) => *pos,
+            None => self.end
+        }
+    }
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut lhs = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let rhs = self.parse_and()?;
+            lhs = Expr::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.pos += 1,
+                Some(Token::Or) | Some(Token::RParen) | None => break,
+                _ => {}  /* implicit AND */
+            }
+            let rhs = self.parse_unary()?;
+            lhs = Expr::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+    fn parse_unary(&mut self) -> Result<Expr, QueryError> {
+        let pos = self.here();
+        let tok = self.tokens.get(self.pos).map(|(tok, _)| tok.clone());
+        self.pos += 1;
+        match tok {
+            Some(Token::Not) => {
+                let e = self.parse_unary()?;
+                Ok(Expr::Not(Box::new(e)))
+            },
+            Some(Token::LParen) => {
+                let e = self.parse_or()?;
+                if self.peek() != Some(&Token::RParen) {
+                    let msg = "expected ')'".to_string();
+                    return Err(QueryError { pos: self.here(), msg });
+                }
+                self.pos += 1;
+                Ok(e)
+            },
+            Some(Token::Word(text)) | Some(Token::Phrase(text)) => {
+                self.add_term(text, false, pos)
+            },
+            Some(Token::Regex(text)) => self.add_term(text, true, pos),
+            Some(tok) => {
+                let msg = format!("unexpected {:?}", tok);
+                Err(QueryError { pos, msg })
+            },
+            None => {
+                let msg = "expected term".to_string();
+                Err(QueryError { pos, msg })
+            }
+        }
+    }
+    fn add_term(&mut self, text: String, is_regex: bool, pos: usize)
+        -> Result<Expr, QueryError> {
+        let rslt = 
+            if is_regex {
+                build_regex(&text, &self.flags, self.ignore_case)
+            }
+            else {
+                regex::RegexBuilder::new(&regex::escape(&text))
+                    .case_insensitive(self.ignore_case)
+                    .build()
+            };
+        let re = rslt.map_err(|e| QueryError { pos, msg: format!("invalid regex: {}", e) })?;
+        self.terms.push(Term { text, is_regex, re });
+        Ok(Expr::Term(self.terms.len() - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse() {
+        let q = Query::new(r#"unsafe AND NOT "// SAFETY""#, false).unwrap();
+        assert_eq!(q.get_terms().len(), 2);
+        assert_eq!(q.get_terms()[1].text, "// SAFETY");
+        let expected = Expr::And(
+            Box::new(Expr::Term(0)),
+            Box::new(Expr::Not(Box::new(Expr::Term(1))))
+        );
+        assert_eq!(q.get_expr(), &expected);
+        let q = Query::new("a OR b c", false).unwrap();
+        let expected = Expr::Or(
+            Box::new(Expr::Term(0)),
+            Box::new(Expr::And(Box::new(Expr::Term(1)), Box::new(Expr::Term(2))))
+        );
+        assert_eq!(q.get_expr(), &expected);
+    }
+    #[test]
+    fn eval() {
+        let q = Query::new(r#"unsafe AND NOT "// SAFETY""#, false).unwrap();
+        assert!(q.is_match("unsafe { foo() }"));
+        assert!(!q.is_match("// SAFETY: checked\nunsafe { foo() }"));
+        let q = Query::new(r"(fn OR struct) AND re:\d+", false).unwrap();
+        assert!(q.is_match("struct S([u8; 4]);"));
+        assert!(!q.is_match("struct S;"));
+        let q = Query::new("(re:(a|b)x) OR y", false).unwrap();
+        assert!(q.is_match("bx"));
+        let spans = Query::new("foo OR NOT bar OR oo", false).unwrap().spans("foo");
+        assert_eq!(spans, vec![(0, 3)]);
+        /* regex flags apply to regex terms, not words */
+        let flags = RegexFlags::parse("is").unwrap();
+        let q = Query::with_flags("re:a.c AND B", false, &flags).unwrap();
+        assert!(q.is_match("A\nC B") && !q.is_match("A\nC b"));
+    }
+    #[test]
+    fn errors() {
+        assert_eq!(Query::new("a AND", false).unwrap_err().pos, 5);
+        assert_eq!(Query::new("(a OR b", false).unwrap_err().pos, 7);
+        assert_eq!(Query::new("a \"bc", false).unwrap_err().pos, 2);
+        assert_eq!(Query::new("a re:(", false).unwrap_err().pos, 2);
+        assert!(Query::new("a ) b", false).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+/////////////////////////////////////////////////////////////
+// TextFinder::text_search::replace.rs                     //
+//   - rewrite matches, show diffs, write files safely     //
+/////////////////////////////////////////////////////////////
+/*
+  Replace mode rewrites the spans found by Finder.
+  - with a regex, replacement may use capture groups, $1,
+    ${name}, and $$ for a literal '$'
+  - text and query matches are replaced literally
+  - dry run shows a unified diff instead of writing
+  - files are written to a temp file in the same directory,
+    then renamed over the original, so an interrupted run
+    leaves either the old file or the new one, never a mix
+*/
+
+use regex::Regex;
+use similar::TextDiff;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/*---------------------------------------------------------
+  Outcome of replace mode for one file, sent to SearchEvent
+  - DryRun holds unified diff of changes not written
+  - Written holds number of matches replaced
+  - Failed holds reason file was not rewritten
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Replaced {
+    DryRun(String),
+    Written(usize),
+    Failed(String)
+}
+
+/*---------------------------------------------------------
+  Return buffer with each span replaced
+  - spans are non-overlapping and in order, as Finder
+    returns them
+  - if re is given, captures of the match at each span are
+    expanded into the replacement
+*/
+pub fn replace_spans(
+    buffer: &str, spans: &[(usize, usize)], re: Option<&Regex>, rep: &str
+) -> String {
+    let mut out = String::with_capacity(buffer.len());
+    let mut pos = 0usize;
+    for &(start, end) in spans {
+        out.push_str(&buffer[pos..start]);
+        match re.and_then(|re| re.captures_at(buffer, start)) {
+            Some(caps) if caps.get(0).map(|m| m.start()) == Some(start) => {
+                caps.expand(rep, &mut out);
+            },
+            _ => out.push_str(rep)
+        }
+        pos = end;
+    }
+    out.push_str(&buffer[pos..]);
+    out
+}
+
+/*-- unified diff of old and new text, with 3 lines of context --*/
+pub fn unified_diff(name: &str, old: &str, new: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{}", name), &format!("b/{}", name))
+        .to_string()
+}
+
+/*-- path of backup file, e.g., lib.rs.bak for suffix ".bak" --*/
+pub fn backup_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/*---------------------------------------------------------
+  Replace contents of path with text
+  - writes and syncs a temp file next to path, then renames
+    it over path, keeping path's permissions
+  - if backup suffix is not empty, original is copied to
+    backup_path first
+  - temp file is removed on any error
+*/
+pub fn write_atomic(path: &Path, text: &str, backup: &str) -> io::Result<()> {
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "path has no file name")
+    })?;
+    let tmp = path.with_file_name(format!(
+        ".{}.{}.tmp", name.to_string_lossy(), std::process::id()
+    ));
+    let rslt = (|| {
+        let mut file = File::create(&tmp)?;
+        file.write_all(text.as_bytes())?;
+        file.sync_all()?;
+        drop(file);
+        fs::set_permissions(&tmp, fs::metadata(path)?.permissions())?;
+        if !backup.is_empty() {
+            fs::copy(path, backup_path(path, backup))?;
+        }
+        fs::rename(&tmp, path)
+    })();
+    if rslt.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    rslt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn replace() {
+        let buffer = "let a = foo(1);\nlet b = foo(22);\n";
+        let re = Regex::new(r"foo\((\d+)\)").unwrap();
+        let spans: Vec<(usize, usize)> =
+            re.find_iter(buffer).map(|m| (m.start(), m.end())).collect();
+        assert_eq!(
+            replace_spans(buffer, &spans, Some(&re), "bar($1, $$)"),
+            "let a = bar(1, $);\nlet b = bar(22, $);\n"
+        );
+        assert_eq!(
+            replace_spans(buffer, &spans, None, "x"),
+            "let a = x;\nlet b = x;\n"
+        );
+        assert_eq!(replace_spans(buffer, &[], Some(&re), "x"), buffer);
+    }
+    #[test]
+    fn diff() {
+        let d = unified_diff("lib.rs", "one\ntwo\nthree\n", "one\n2\nthree\n");
+        assert!(d.starts_with("--- a/lib.rs\n+++ b/lib.rs\n@@ -1,3 +1,3 @@\n"));
+        assert!(d.contains("\n-two\n+2\n"));
+        assert!(unified_diff("lib.rs", "same\n", "same\n").is_empty());
+    }
+    #[test]
+    fn atomic() {
+        let dir = std::env::temp_dir().join(format!("replace_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("src.txt");
+        fs::write(&path, "old text\n").unwrap();
+        write_atomic(&path, "new text\n", ".bak").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new text\n");
+        assert_eq!(fs::read_to_string(backup_path(&path, ".bak")).unwrap(), "old text\n");
+        /* missing file fails and leaves no temp file behind */
+        assert!(write_atomic(&dir.join("none.txt"), "x", "").is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
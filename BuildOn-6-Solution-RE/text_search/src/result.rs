@@ -0,0 +1,123 @@
+/////////////////////////////////////////////////////////////
+// TextFinder::text_search::result.rs                      //
+//   - results for one file, sent to SearchEvent           //
+/////////////////////////////////////////////////////////////
+/*
+  FileResult carries everything Finder found for one file.
+  - it is non_exhaustive, so fields can be added without
+    breaking SearchEvent implementations in other crates,
+    which read fields but build results with FileResult::new
+  - version will be bumped if fields are added, so clients
+    can tell which fields are filled in
+
+  FileError is sent, just before a FileResult with Error
+  status, for files that could not be opened, decompressed,
+  or read.  It adds the io::ErrorKind of the failure.
+*/
+
+use crate::{CaptureSet, ContextBlock, Item, Match, Replaced};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/*-- version of FileResult fields --*/
+pub const FILE_RESULT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum FileStatus {
+    /*-- file has matches --*/
+    Matched,
+    /*-- file was searched, no matches --*/
+    #[default]
+    Unmatched,
+    /*-- file could not be opened, decompressed, or read, see message --*/
+    Error,
+    /*-- file was not searched, e.g., binary, see message --*/
+    Skipped
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct FileResult {
+    pub version: u32,
+    /*-- file name, relative to dir last sent with set_dir --*/
+    pub path: PathBuf,
+    pub status: FileStatus,
+    /*-- reason for Error or Skipped status, or note on results --*/
+    pub message: String,
+    /*-- search text, regex, or query --*/
+    pub pattern: String,
+    /*-- number of matches, even if not listed --*/
+    pub count: usize,
+    /*-- matches, empty for count only and context modes --*/
+    pub matches: Vec<Match>,
+    /*-- context blocks, if context lines were requested --*/
+    pub blocks: Vec<ContextBlock>,
+    /*-- items matched by item search, see items.rs --*/
+    pub items: Vec<Item>,
+    /*-- binary file matches, searched with BinaryPolicy::Match --*/
+    pub binary: bool,
+    /*-- terms found, with counts, for terms search --*/
+    pub terms: Vec<(String, usize)>,
+    /*-- capture groups of each match, when extracting --*/
+    pub captures: Vec<CaptureSet>,
+    /*-- diff or outcome of rewriting file, in replace mode --*/
+    pub replaced: Option<Replaced>,
+    /*-- size of file in bytes --*/
+    pub bytes: u64,
+    /*-- time to read and search file --*/
+    pub elapsed: Duration
+}
+impl FileResult {
+    pub fn new(path: &Path, status: FileStatus, pattern: &str) -> Self {
+        FileResult {
+            version: FILE_RESULT_VERSION,
+            path: path.to_path_buf(),
+            status,
+            pattern: pattern.to_string(),
+            ..Default::default()
+        }
+    }
+    pub fn is_match(&self) -> bool {
+        self.status == FileStatus::Matched
+    }
+}
+
+/*-- file that could not be searched, with cause --*/
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct FileError {
+    /*-- file name, relative to dir last sent with set_dir --*/
+    pub path: PathBuf,
+    pub kind: io::ErrorKind,
+    /*-- what failed and why, e.g., "can't open file: ..." --*/
+    pub message: String
+}
+impl FileError {
+    pub fn new(path: &Path, action: &str, err: &io::Error) -> Self {
+        FileError {
+            path: path.to_path_buf(),
+            kind: err.kind(),
+            message: format!("can't {} file: {}", action, err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn construct() {
+        let fr = FileResult::new(Path::new("lib.rs"), FileStatus::Matched, "abc");
+        assert_eq!(fr.version, FILE_RESULT_VERSION);
+        assert_eq!(fr.path, PathBuf::from("lib.rs"));
+        assert!(fr.is_match());
+        assert!(fr.matches.is_empty() && fr.message.is_empty());
+        assert!(!FileResult::default().is_match());
+        let err = io::Error::new(io::ErrorKind::NotFound, "gone");
+        let fe = FileError::new(Path::new("lib.rs"), "open", &err);
+        assert_eq!(fe.kind, io::ErrorKind::NotFound);
+        assert_eq!(fe.message, "can't open file: gone");
+    }
+}
@@ -0,0 +1,289 @@
+/////////////////////////////////////////////////////////////
+// TextFinder::text_search::scope.rs                       //
+//   - split Rust source into code, comments, and strings  //
+/////////////////////////////////////////////////////////////
+/*
+  A small lexer that classifies each byte of Rust source as
+  code, comment, or string literal, so Finder can match only
+  in selected scopes.
+  - doc comments are ///, //!, and block comments opened
+    with slash, star, star or slash, star, bang, but not
+    //// or three stars, as rustc reads them
+  - block comments nest
+  - char and byte literals, e.g., '"', are string literals,
+    lifetimes and labels are code
+  - unterminated comments and strings run to end of source
+*/
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Code,
+    LineComment,
+    BlockComment,
+    DocComment,
+    Str,
+    RawStr
+}
+impl Scope {
+    /*-----------------------------------------------------
+      Scopes for name used on command line
+      - code, line, block, doc, string, raw
+      - comments is line, block, and doc
+      - strings is string and raw
+    */
+    pub fn parse(name: &str) -> Option<Vec<Scope>> {
+        let scopes = match name {
+            "code" => vec![Scope::Code],
+            "line" => vec![Scope::LineComment],
+            "block" => vec![Scope::BlockComment],
+            "doc" => vec![Scope::DocComment],
+            "string" => vec![Scope::Str],
+            "raw" => vec![Scope::RawStr],
+            "comments" => vec![Scope::LineComment, Scope::BlockComment, Scope::DocComment],
+            "strings" => vec![Scope::Str, Scope::RawStr],
+            _ => return None
+        };
+        Some(scopes)
+    }
+}
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Scope::Code => "code",
+            Scope::LineComment => "line",
+            Scope::BlockComment => "block",
+            Scope::DocComment => "doc",
+            Scope::Str => "string",
+            Scope::RawStr => "raw"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/*-- bytes start..end of source, all in one scope --*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub scope: Scope,
+    pub start: usize,
+    pub end: usize
+}
+
+/*---------------------------------------------------------
+  Regions of src, in order, covering all of it
+  - delimiters are ASCII, so scanning bytes never splits
+    a UTF-8 char
+*/
+pub fn regions(src: &str) -> Vec<Region> {
+    let b = src.as_bytes();
+    let mut rgns = Vec::<Region>::new();
+    let mut code_start = 0usize;
+    let mut i = 0usize;
+    while i < b.len() {
+        let (scope, end) = match b[i] {
+            b'/' if at(b, i + 1) == b'/' => {
+                let end = b[i..].iter().position(|&c| c == b'\n').map_or(b.len(), |p| i + p);
+                let doc = (at(b, i + 2) == b'/' && at(b, i + 3) != b'/') || at(b, i + 2) == b'!';
+                (if doc { Scope::DocComment } else { Scope::LineComment }, end)
+            },
+            b'/' if at(b, i + 1) == b'*' => {
+                let doc = (at(b, i + 2) == b'*' && !matches!(at(b, i + 3), b'*' | b'/'))
+                          || at(b, i + 2) == b'!';
+                (if doc { Scope::DocComment } else { Scope::BlockComment }, block_end(b, i))
+            },
+            b'"' => (Scope::Str, str_end(b, i + 1)),
+            b'r' if is_raw_start(b, i) => (Scope::RawStr, raw_end(b, i)),
+            b'\'' => match char_end(src, i) {
+                Some(end) => (Scope::Str, end),
+                None => {  /* lifetime or label */
+                    i += 1;
+                    continue;
+                }
+            },
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        if i > code_start {
+            rgns.push(Region { scope: Scope::Code, start: code_start, end: i });
+        }
+        rgns.push(Region { scope, start: i, end });
+        i = end;
+        code_start = end;
+    }
+    if b.len() > code_start {
+        rgns.push(Region { scope: Scope::Code, start: code_start, end: b.len() });
+    }
+    rgns
+}
+
+/*---------------------------------------------------------
+  Keep spans lying wholly in regions with selected scopes
+  - spans are byte ranges into the source regions were
+    made from
+*/
+pub fn retain_in_scopes(rgns: &[Region], scopes: &[Scope], spans: &mut Vec<(usize, usize)>) {
+    spans.retain(|&(start, end)| {
+        let first = rgns.partition_point(|r| r.end <= start);
+        let last = end.max(start + 1);
+        rgns[first..].iter()
+            .take_while(|r| r.start < last)
+            .all(|r| scopes.contains(&r.scope))
+    });
+}
+
+/*-- byte at i, or 0 past end --*/
+fn at(b: &[u8], i: usize) -> u8 {
+    b.get(i).copied().unwrap_or(0)
+}
+fn is_ident(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c >= 0x80
+}
+/*-- end of nested block comment starting at i --*/
+fn block_end(b: &[u8], i: usize) -> usize {
+    let mut depth = 0usize;
+    let mut j = i;
+    while j < b.len() {
+        if b[j] == b'/' && at(b, j + 1) == b'*' {
+            depth += 1;
+            j += 2;
+        }
+        else if b[j] == b'*' && at(b, j + 1) == b'/' {
+            depth -= 1;
+            j += 2;
+            if depth == 0 {
+                return j;
+            }
+        }
+        else {
+            j += 1;
+        }
+    }
+    b.len()
+}
+/*-- end of string whose text starts at j, after the quote --*/
+fn str_end(b: &[u8], mut j: usize) -> usize {
+    while j < b.len() {
+        match b[j] {
+            b'\\' => j += 2,
+            b'"' => return j + 1,
+            _ => j += 1
+        }
+    }
+    b.len()
+}
+/*-- r"..", r#".."#, and with b or c prefix, but not r#ident --*/
+fn is_raw_start(b: &[u8], i: usize) -> bool {
+    let prefix_ok =
+        i == 0 || !is_ident(b[i - 1])
+        || (matches!(b[i - 1], b'b' | b'c') && (i < 2 || !is_ident(b[i - 2])));
+    if !prefix_ok {
+        return false;
+    }
+    let mut j = i + 1;
+    while at(b, j) == b'#' {
+        j += 1;
+    }
+    at(b, j) == b'"'
+}
+/*-- end of raw string starting at r, closed by quote and same number of #s --*/
+fn raw_end(b: &[u8], i: usize) -> usize {
+    let mut j = i + 1;
+    let mut hashes = 0usize;
+    while b[j] == b'#' {
+        hashes += 1;
+        j += 1;
+    }
+    j += 1;  /* opening quote */
+    while j < b.len() {
+        if b[j] == b'"' && b[j + 1..].iter().take(hashes).filter(|&&c| c == b'#').count() == hashes {
+            return j + 1 + hashes;
+        }
+        j += 1;
+    }
+    b.len()
+}
+/*-- end of char literal starting at quote i, None for lifetime --*/
+fn char_end(src: &str, i: usize) -> Option<usize> {
+    let b = src.as_bytes();
+    if at(b, i + 1) == b'\\' {
+        /* escape, e.g., '\n', '\'', '\u{1F600}' */
+        let p = b.get(i + 3..)?.iter().take(10).position(|&c| c == b'\'')?;
+        return Some(i + 3 + p + 1);
+    }
+    let c = src[i + 1..].chars().next()?;
+    let k = i + 1 + c.len_utf8();
+    if c != '\'' && at(b, k) == b'\'' {
+        Some(k + 1)
+    }
+    else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    /* (scope, text) of each region */
+    fn lex(src: &str) -> Vec<(Scope, &str)> {
+        regions(src).iter().map(|r| (r.scope, &src[r.start..r.end])).collect()
+    }
+    #[test]
+    fn comments() {
+        let src = "let x = 1; // one\n/// doc\n//// not doc\n/* a /* nested */ b */ y /** d */ /**/";
+        assert_eq!(lex(src), vec![
+            (Scope::Code, "let x = 1; "),
+            (Scope::LineComment, "// one"),
+            (Scope::Code, "\n"),
+            (Scope::DocComment, "/// doc"),
+            (Scope::Code, "\n"),
+            (Scope::LineComment, "//// not doc"),
+            (Scope::Code, "\n"),
+            (Scope::BlockComment, "/* a /* nested */ b */"),
+            (Scope::Code, " y "),
+            (Scope::DocComment, "/** d */"),
+            (Scope::Code, " "),
+            (Scope::BlockComment, "/**/")
+        ]);
+        assert_eq!(lex("//! crate doc")[0].0, Scope::DocComment);
+        assert_eq!(lex("/* open")[0], (Scope::BlockComment, "/* open"));
+    }
+    #[test]
+    fn strings() {
+        let src = r####"f("a \" // b", r#"raw "q" "#, br"x", '"', b'\'', 'é', '\u{1F600}');"####;
+        let strs: Vec<(Scope, &str)> =
+            lex(src).into_iter().filter(|(s, _)| *s != Scope::Code).collect();
+        assert_eq!(strs, vec![
+            (Scope::Str, r#""a \" // b""#),
+            (Scope::RawStr, r###"r#"raw "q" "#"###),
+            (Scope::RawStr, r#"r"x""#),
+            (Scope::Str, "'\"'"),
+            (Scope::Str, r"'\''"),
+            (Scope::Str, "'é'"),
+            (Scope::Str, r"'\u{1F600}'")
+        ]);
+        /* lifetimes, labels, raw identifiers, and names ending in r are code */
+        let src = "fn f<'a>(x: &'a str) { 'outer: loop {} let r#type = 1; for_r(r#type); }";
+        assert_eq!(lex(src), vec![(Scope::Code, src)]);
+    }
+    #[test]
+    fn retain() {
+        let src = "let s = \"needle\"; // needle\nneedle";
+        let rgns = regions(src);
+        let all: Vec<(usize, usize)> = src.match_indices("needle").map(|(i, _)| (i, i + 6)).collect();
+        let mut spans = all.clone();
+        retain_in_scopes(&rgns, &[Scope::Code], &mut spans);
+        assert_eq!(spans, vec![all[2]]);
+        let mut spans = all.clone();
+        retain_in_scopes(&rgns, &Scope::parse("comments").unwrap(), &mut spans);
+        assert_eq!(spans, vec![all[1]]);
+        /* span crossing from string into code is in neither */
+        let mut spans = vec![(10, 20)];
+        retain_in_scopes(&rgns, &[Scope::Str], &mut spans);
+        assert!(spans.is_empty());
+        assert_eq!(Scope::parse("strings"), Some(vec![Scope::Str, Scope::RawStr]));
+        assert_eq!(Scope::parse("bogus"), None);
+    }
+}
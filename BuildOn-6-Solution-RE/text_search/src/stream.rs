@@ -0,0 +1,265 @@
+/////////////////////////////////////////////////////////////
+// TextFinder::text_search::stream.rs                      //
+//   - bounded-memory, line by line reading of files       //
+/////////////////////////////////////////////////////////////
+/*
+  LineStream reads a source in fixed-size chunks and hands
+  out complete lines, so files are searched without loading
+  them into memory.
+  - a line split across chunks is carried into next chunk,
+    so matches crossing chunk boundaries are found
+  - memory use is bounded by the buffer size, or the length
+    of the longest line, if that is larger
+
+  ContextCollector builds ContextBlocks from lines as they
+  stream by, keeping only the last few lines.
+
+  Matches and context lines kept for a file are capped at
+  MAX_LISTED, so dense matches in a huge file don't undo the
+  memory bound.  Matches past the cap are still counted.
+*/
+
+use crate::{ContextBlock, ContextLine};
+use encoding_rs::Encoding;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+/*-- default chunk size for streaming reads --*/
+pub const DEFAULT_BUF_SIZE: usize = 64 * 1024;
+/*-- most matches, or context lines, kept for one streamed file --*/
+pub const MAX_LISTED: usize = 10_000;
+
+/*-- one line handed out by LineStream --*/
+#[derive(Debug, PartialEq)]
+pub struct StreamLine<'a> {
+    /*-- 1-based line number --*/
+    pub line: usize,
+    /*-- byte offset of line start from start of source --*/
+    pub offset: usize,
+    /*-- line text without "\n" or "\r\n" terminator --*/
+    pub text: Cow<'a, str>
+}
+
+#[derive(Debug)]
+pub struct LineStream<R: Read> {
+    rdr: R,
+    buf: Vec<u8>,
+    chunk: Vec<u8>,
+    pos: usize,
+    line: usize,
+    offset: usize,
+    eof: bool,
+    lossy: bool,
+    fallback: Option<&'static Encoding>
+}
+impl<R: Read> LineStream<R> {
+    pub fn new(rdr: R, buf_size: usize) -> Self {
+        LineStream {
+            rdr,
+            buf: Vec::with_capacity(buf_size.max(1)),
+            chunk: vec![0u8; buf_size.max(1)],
+            pos: 0,
+            line: 0,
+            offset: 0,
+            eof: false,
+            lossy: false,
+            fallback: None
+        }
+    }
+    /*-- replace invalid UTF-8 with U+FFFD instead of failing --*/
+    pub fn set_lossy(&mut self, lossy: bool) {
+        self.lossy = lossy;
+    }
+    /*-- decode lines that are not UTF-8 with enc, instead of failing --*/
+    pub fn set_fallback(&mut self, enc: Option<&'static Encoding>) {
+        self.fallback = enc;
+    }
+    /*-----------------------------------------------------
+      Return next complete line, or None at end of source.
+      Lines that are not valid UTF-8 are decoded with the
+      fallback encoding, or made lossy, else they return an
+      InvalidData error, as read_to_string does.  Decoded
+      and lossy lines are returned as Cow::Owned.
+    */
+    pub fn next_line(&mut self) -> io::Result<Option<StreamLine<'_>>> {
+        let (start, end, next) = loop {
+            let rest = &self.buf[self.pos..];
+            if let Some(i) = rest.iter().position(|&b| b == b'\n') {
+                break (self.pos, self.pos + i, self.pos + i + 1);
+            }
+            if self.eof {
+                if rest.is_empty() {
+                    return Ok(None);
+                }
+                break (self.pos, self.buf.len(), self.buf.len());
+            }
+            self.fill()?;
+        };
+        let mut bytes = &self.buf[start..end];
+        if bytes.last() == Some(&b'\r') {
+            bytes = &bytes[..bytes.len() - 1];
+        }
+        let text = match std::str::from_utf8(bytes) {
+            Ok(text) => Cow::Borrowed(text),
+            Err(e) => match self.fallback {
+                Some(enc) => Cow::Owned(enc.decode_without_bom_handling(bytes).0.into_owned()),
+                None if self.lossy => String::from_utf8_lossy(bytes),
+                None => return Err(io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+        };
+        self.line += 1;
+        let offset = self.offset;
+        self.offset += next - start;
+        self.pos = next;
+        Ok(Some(StreamLine { line: self.line, offset, text }))
+    }
+    /*-- drop consumed bytes, then append next chunk from source --*/
+    fn fill(&mut self) -> io::Result<()> {
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+        let n = loop {
+            match self.rdr.read(&mut self.chunk) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e)
+            }
+        };
+        if n == 0 {
+            self.eof = true;
+        }
+        self.buf.extend_from_slice(&self.chunk[..n]);
+        Ok(())
+    }
+}
+
+/*---------------------------------------------------------
+  Collects context blocks from a stream of lines
+  - holds at most before lines not yet in a block
+  - gives the same blocks as context_blocks in lib.rs, up
+    to max_lines, then starts no new blocks
+*/
+#[derive(Debug, Default)]
+pub struct ContextCollector {
+    before: usize,
+    after: usize,
+    recent: VecDeque<ContextLine>,
+    blocks: Vec<ContextBlock>,
+    last: usize,
+    after_left: usize,
+    lines: usize,
+    max_lines: usize,
+    truncated: bool
+}
+impl ContextCollector {
+    pub fn new(before: usize, after: usize) -> Self {
+        ContextCollector {
+            before,
+            after,
+            max_lines: usize::MAX,
+            ..Default::default()
+        }
+    }
+    /*-- lines kept in blocks, a block may run past it by its trailing lines --*/
+    pub fn set_max_lines(&mut self, n: usize) {
+        self.max_lines = n;
+    }
+    /*-- were matches dropped at max_lines? --*/
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+    /*-- called for every line in order --*/
+    pub fn push(&mut self, line: usize, text: &str, is_match: bool) {
+        let full = self.lines >= self.max_lines;
+        self.truncated |= is_match && full;
+        if is_match && !full {
+            self.lines += self.recent.len() + 1;
+            let first = self.recent.front().map_or(line, |cl| cl.line);
+            if self.blocks.is_empty() || first > self.last + 1 {
+                self.blocks.push(ContextBlock::new());
+            }
+            let block = self.blocks.last_mut().unwrap();
+            block.extend(self.recent.drain(..));
+            block.push(ContextLine { line, text: text.to_string(), is_match });
+            self.last = line;
+            self.after_left = self.after;
+        }
+        else if self.after_left > 0 {
+            let block = self.blocks.last_mut().unwrap();
+            block.push(ContextLine { line, text: text.to_string(), is_match });
+            self.last = line;
+            self.lines += 1;
+            self.after_left -= 1;
+        }
+        else if self.before > 0 && !full {
+            self.recent.push_back(ContextLine { line, text: text.to_string(), is_match });
+            if self.recent.len() > self.before {
+                self.recent.pop_front();
+            }
+        }
+    }
+    pub fn into_blocks(self) -> Vec<ContextBlock> {
+        self.blocks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn small_chunks() {
+        let src = "hello world\nfoo\r\n\nbär baz";
+        let mut ls = LineStream::new(src.as_bytes(), 3);
+        let mut lines = Vec::<(usize, usize, String)>::new();
+        while let Some(sl) = ls.next_line().unwrap() {
+            lines.push((sl.line, sl.offset, sl.text.to_string()));
+        }
+        let expected = vec![
+            (1, 0, "hello world".to_string()),
+            (2, 12, "foo".to_string()),
+            (3, 17, "".to_string()),
+            (4, 18, "bär baz".to_string())
+        ];
+        assert_eq!(lines, expected);
+    }
+    #[test]
+    fn invalid_utf8() {
+        let src: &[u8] = b"ok\n\xff\xfe\n";
+        let mut ls = LineStream::new(src, 16);
+        assert!(ls.next_line().unwrap().is_some());
+        assert!(ls.next_line().is_err());
+        let mut ls = LineStream::new(src, 16);
+        ls.set_lossy(true);
+        ls.next_line().unwrap();
+        let sl = ls.next_line().unwrap().unwrap();
+        assert_eq!(sl.text, "\u{FFFD}\u{FFFD}");
+        assert!(matches!(sl.text, Cow::Owned(_)));
+        let mut ls = LineStream::new(&b"caf\xe9\n"[..], 16);
+        ls.set_fallback(Some(encoding_rs::WINDOWS_1252));
+        assert_eq!(ls.next_line().unwrap().unwrap().text, "caf\u{e9}");
+    }
+    #[test]
+    fn collector() {
+        let buffer = "l1\nfoo\nl3\nl4\nfoo\nl6\nl7\nl8\nl9\nfoo";
+        let mut cc = ContextCollector::new(1, 1);
+        for (i, text) in buffer.lines().enumerate() {
+            cc.push(i + 1, text, text == "foo");
+        }
+        let blocks = cc.into_blocks();
+        assert_eq!(blocks.len(), 2);
+        let lines: Vec<usize> = blocks[0].iter().map(|cl| cl.line).collect();
+        assert_eq!(lines, vec![1, 2, 3, 4, 5, 6]);
+        let lines: Vec<usize> = blocks[1].iter().map(|cl| cl.line).collect();
+        assert_eq!(lines, vec![9, 10]);
+        /* first block fills the cap, last match is dropped */
+        let mut cc = ContextCollector::new(1, 1);
+        cc.set_max_lines(5);
+        for (i, text) in buffer.lines().enumerate() {
+            cc.push(i + 1, text, text == "foo");
+        }
+        assert!(cc.is_truncated());
+        let blocks = cc.into_blocks();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].len(), 6);
+    }
+}
@@ -0,0 +1,139 @@
+/////////////////////////////////////////////////////////////
+// TextFinder::text_search::terms.rs                       //
+//   - search for many literal terms in one pass           //
+/////////////////////////////////////////////////////////////
+/*
+  Terms holds a list of literal terms, e.g., banned API
+  names, compiled into one Aho-Corasick automaton, so each
+  file is scanned once, however many terms there are.
+  - overlapping terms resolve to the leftmost, then longest
+  - Aho-Corasick folds only ASCII letters, so ignore_case
+    with any non-ASCII term uses a regex alternation, with
+    Unicode case folding, instead
+  - term files hold one term per line, blank lines and lines
+    starting with '#' are skipped
+*/
+
+use aho_corasick::{AhoCorasick, MatchKind};
+use regex::{Regex, RegexBuilder};
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+enum Searcher {
+    Ac(AhoCorasick),
+    /*-- longest terms first, group i + 1 matches terms[order[i]] --*/
+    Re(Regex, Vec<usize>)
+}
+
+#[derive(Debug, Clone)]
+pub struct Terms {
+    terms: Vec<String>,
+    srch: Searcher
+}
+impl Terms {
+    pub fn new(terms: &[String], ignore_case: bool) -> Result<Terms, String> {
+        let terms: Vec<String> =
+            terms.iter().filter(|t| !t.is_empty()).cloned().collect();
+        if terms.is_empty() {
+            return Err("no search terms".to_string());
+        }
+        if ignore_case && terms.iter().any(|t| !t.is_ascii()) {
+            let mut order: Vec<usize> = (0..terms.len()).collect();
+            order.sort_by_key(|&i| std::cmp::Reverse(terms[i].len()));
+            let alts: Vec<String> = order.iter()
+                .map(|&i| format!("({})", regex::escape(&terms[i])))
+                .collect();
+            let re = RegexBuilder::new(&alts.join("|"))
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| e.to_string())?;
+            return Ok(Terms { terms, srch: Searcher::Re(re, order) });
+        }
+        let ac = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostLongest)
+            .ascii_case_insensitive(ignore_case)
+            .build(&terms)
+            .map_err(|e| e.to_string())?;
+        Ok(Terms { terms, srch: Searcher::Ac(ac) })
+    }
+    pub fn get_terms(&self) -> &[String] {
+        &self.terms
+    }
+    /*-- byte spans (start, end) of all term matches in buffer --*/
+    pub fn spans(&self, buffer: &str) -> Vec<(usize, usize)> {
+        match &self.srch {
+            Searcher::Ac(ac) => ac.find_iter(buffer).map(|m| (m.start(), m.end())).collect(),
+            Searcher::Re(re, _) => re.find_iter(buffer).map(|m| (m.start(), m.end())).collect()
+        }
+    }
+    /*-- index of term matching all of text, a span returned by spans --*/
+    pub fn term_index(&self, text: &str) -> Option<usize> {
+        match &self.srch {
+            Searcher::Ac(ac) => ac.find(text)
+                .filter(|m| m.start() == 0 && m.end() == text.len())
+                .map(|m| m.pattern().as_usize()),
+            Searcher::Re(re, order) => {
+                let caps = re.captures(text)?;
+                let whole = caps.get(0)?;
+                if whole.start() != 0 || whole.end() != text.len() {
+                    return None;
+                }
+                (1..caps.len()).find(|&g| caps.get(g).is_some()).map(|g| order[g - 1])
+            }
+        }
+    }
+}
+
+/*-- read terms from file, one per line --*/
+pub fn read_terms(path: &Path) -> io::Result<Vec<String>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(text.lines()
+           .map(|l| l.trim())
+           .filter(|l| !l.is_empty() && !l.starts_with('#'))
+           .map(|l| l.to_string())
+           .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn strs(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+    #[test]
+    fn find_terms() {
+        let t = Terms::new(&strs(&["unwrap", "unwrap_or", "", "transmute"]), false).unwrap();
+        assert_eq!(t.get_terms().len(), 3);
+        let buffer = "x.unwrap_or(0); y.unwrap(); mem::transmute(z)";
+        let spans = t.spans(buffer);
+        let found: Vec<&str> = spans.iter().map(|&(s, e)| &buffer[s..e]).collect();
+        assert_eq!(found, vec!["unwrap_or", "unwrap", "transmute"]);
+        let ids: Vec<Option<usize>> =
+            spans.iter().map(|&(s, e)| t.term_index(&buffer[s..e])).collect();
+        assert_eq!(ids, vec![Some(1), Some(0), Some(2)]);
+        assert!(Terms::new(&strs(&[""]), false).is_err());
+        let t = Terms::new(&strs(&["Unwrap"]), true).unwrap();
+        assert_eq!(t.spans(buffer).len(), 2);
+    }
+    #[test]
+    fn fold_unicode() {
+        let t = Terms::new(&strs(&["straße", "Ärger", "ärgerlich"]), true).unwrap();
+        let buffer = "STRAẞE, ärger, ÄRGERLICH";
+        let spans = t.spans(buffer);
+        let found: Vec<&str> = spans.iter().map(|&(s, e)| &buffer[s..e]).collect();
+        assert_eq!(found, vec!["STRAẞE", "ärger", "ÄRGERLICH"]);
+        let ids: Vec<Option<usize>> =
+            spans.iter().map(|&(s, e)| t.term_index(&buffer[s..e])).collect();
+        assert_eq!(ids, vec![Some(0), Some(1), Some(2)]);
+        assert!(Terms::new(&strs(&["straße"]), false).unwrap().spans(buffer).is_empty());
+    }
+    #[test]
+    fn term_file() {
+        let path = std::env::temp_dir().join(format!("terms_test_{}.txt", std::process::id()));
+        std::fs::write(&path, "# banned\nunwrap\n\n  expect  \n").unwrap();
+        assert_eq!(read_terms(&path).unwrap(), strs(&["unwrap", "expect"]));
+        std::fs::remove_file(&path).unwrap();
+        assert!(read_terms(&path).is_err());
+    }
+}